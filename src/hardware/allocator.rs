@@ -0,0 +1,411 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    Programs, State_Process,
+    hardware::{
+        architecture::Palabra,
+        mmu::{NUM_FRAMES, PAGE_SIZE, PAGE_TABLE_AREA_END, PAGE_TABLE_AREA_START, frame_address},
+        ram::Ram,
+    },
+    utils::{Errors, convert_option_result, convert_to_string_format_pal},
+};
+
+use super::interrupts::Interrups;
+
+//Area de memoria de usuario, lo anterior es del sistema operativo
+pub const USER_MEM_START: i32 = 301;
+pub const USER_MEM_END: i32 = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fit_Policy {
+    First,
+    Best,
+    Worst,
+    Next,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Hole {
+    pub start: i32,
+    pub size: i32,
+}
+
+#[derive(Debug)]
+pub struct Memory_Manager {
+    pub policy: Fit_Policy,
+    //Donde termino la ultima asignación, desde ahi busca next fit
+    pub last_position: i32,
+    //Los programas nuevos se cargan paginados en vez de contiguos
+    pub paging: bool,
+}
+
+impl Memory_Manager {
+    pub fn new() -> Self {
+        Memory_Manager {
+            policy: Fit_Policy::First,
+            last_position: USER_MEM_START,
+            paging: false,
+        }
+    }
+
+    pub fn policy_by_name(name: &str) -> Option<Fit_Policy> {
+        match name {
+            "first" => Some(Fit_Policy::First),
+            "best" => Some(Fit_Policy::Best),
+            "worst" => Some(Fit_Policy::Worst),
+            "next" => Some(Fit_Policy::Next),
+            _ => None,
+        }
+    }
+
+    //Palabras que ocupa un programa en memoria, de rb a rl inclusive
+    pub fn size_program(program: &Programs) -> i32 {
+        program.num_instruccions_with_pila + 1
+    }
+
+    //Rangos [inicio, fin) ocupados en el area de usuario: los procesos contiguos y los marcos de los paginados
+    fn used_ranges(table_process: &Vec<Programs>) -> Vec<(i32, i32)> {
+        let mut used = vec![];
+        for p in table_process.iter().filter(|p| p.swap_sector < 0) {
+            if p.ptbr >= 0 {
+                for &(_, frame) in &p.page_frames {
+                    used.push((frame_address(frame), frame_address(frame) + PAGE_SIZE));
+                }
+            } else {
+                used.push((p.pos_start_mem, p.pos_start_mem + Self::size_program(p)));
+            }
+        }
+        used.sort();
+        used
+    }
+
+    //Lista de huecos libres del area de usuario ordenada por dirección, a partir de los procesos en memoria
+    pub fn holes(table_process: &Vec<Programs>) -> Vec<Hole> {
+        let used = Self::used_ranges(table_process);
+
+        let mut holes = vec![];
+        let mut pos = USER_MEM_START;
+        for (start, end) in used {
+            if start > pos {
+                holes.push(Hole {
+                    start: pos,
+                    size: start - pos,
+                });
+            }
+            pos = pos.max(end);
+        }
+        if pos <= USER_MEM_END {
+            holes.push(Hole {
+                start: pos,
+                size: USER_MEM_END + 1 - pos,
+            });
+        }
+        holes
+    }
+
+    //Busca un hueco para size palabras según la politica, devuelve la dirección de inicio
+    pub fn allocate(&mut self, table_process: &Vec<Programs>, size: i32) -> Option<i32> {
+        let holes = Self::holes(table_process);
+        let fits = holes.iter().filter(|h| h.size >= size);
+
+        let hole = match self.policy {
+            Fit_Policy::First => fits.min_by_key(|h| h.start),
+            Fit_Policy::Best => fits.min_by_key(|h| (h.size, h.start)),
+            Fit_Policy::Worst => fits.max_by_key(|h| (h.size, -h.start)),
+            Fit_Policy::Next => {
+                let fits: Vec<&Hole> = fits.collect();
+                let last = self.last_position;
+                //Primero el hueco que contiene o sigue a la ultima asignación, si no se da la vuelta
+                fits.iter()
+                    .find(|h| h.start + h.size > last)
+                    .or(fits.first())
+                    .copied()
+            }
+        }?;
+
+        //En next fit se puede empezar en medio del hueco donde quedo la ultima asignación
+        let start = if self.policy == Fit_Policy::Next
+            && hole.start < self.last_position
+            && self.last_position + size <= hole.start + hole.size
+        {
+            self.last_position
+        } else {
+            hole.start
+        };
+
+        self.last_position = start + size;
+        Some(start)
+    }
+
+    //Marcos que estan completos dentro de algun hueco
+    pub fn free_frames(table_process: &Vec<Programs>) -> Vec<i32> {
+        let holes = Self::holes(table_process);
+        (0..NUM_FRAMES)
+            .filter(|&frame| {
+                let start = frame_address(frame);
+                holes
+                    .iter()
+                    .any(|h| h.start <= start && start + PAGE_SIZE <= h.start + h.size)
+            })
+            .collect()
+    }
+
+    //Primer lugar del area de tablas de paginas donde entran pages entradas
+    pub fn allocate_page_table(table_process: &Vec<Programs>, pages: i32) -> Option<i32> {
+        let mut used: Vec<(i32, i32)> = table_process
+            .iter()
+            .filter(|p| p.ptbr >= 0)
+            .map(|p| (p.ptbr, p.ptbr + p.pages))
+            .collect();
+        used.sort();
+
+        let mut pos = PAGE_TABLE_AREA_START;
+        for (start, end) in used {
+            if start - pos >= pages {
+                return Some(pos);
+            }
+            pos = pos.max(end);
+        }
+        if PAGE_TABLE_AREA_END + 1 - pos >= pages {
+            return Some(pos);
+        }
+        None
+    }
+
+    //Memoria libre total del area de usuario
+    pub fn free_memory(table_process: &Vec<Programs>) -> i32 {
+        Self::holes(table_process).iter().map(|h| h.size).sum()
+    }
+
+    //Desliza los procesos hacia abajo para juntar los huecos, devuelve cuantos procesos se movieron.
+    //Los procesos bloqueados o con E/S pendiente no se mueven porque el dma puede estar escribiendo en
+    //su memoria, y los marcos de los procesos paginados quedan fijos
+    pub fn compact(
+        table_process: &mut Vec<Programs>,
+        ram: &Arc<Mutex<Ram>>,
+    ) -> Result<u32, Errors> {
+        //Bloques (inicio, tamaño, posición en la tabla si se puede mover)
+        let mut blocks: Vec<(i32, i32, Option<usize>)> = vec![];
+        for (pos, p) in table_process.iter().enumerate() {
+            if p.swap_sector >= 0 {
+                continue;
+            } else if p.ptbr >= 0 {
+                for &(_, frame) in &p.page_frames {
+                    blocks.push((frame_address(frame), PAGE_SIZE, None));
+                }
+            } else if p.state == State_Process::Blocked || p.pending_io > 0 {
+                blocks.push((p.pos_start_mem, Self::size_program(p), None));
+            } else {
+                blocks.push((p.pos_start_mem, Self::size_program(p), Some(pos)));
+            }
+        }
+        blocks.sort_by_key(|b| b.0);
+
+        let mut ram = ram.lock().unwrap();
+        let mut next_free = USER_MEM_START;
+        let mut moved = 0;
+
+        for (start, size, movable) in blocks {
+            let pos = match movable {
+                Some(pos) if start > next_free => pos,
+                _ => {
+                    next_free = next_free.max(start + size);
+                    continue;
+                }
+            };
+
+            //Se copia de abajo hacia arriba, el destino siempre esta antes que el origen
+            for i in 0..size {
+                let pal = ram.readMemory(start + i)?;
+                ram.writeMemory(next_free + i, pal)?;
+            }
+            //Se limpia lo que quedo del rango viejo sin pisar por el nuevo
+            let clear_from = start.max(next_free + size);
+            if clear_from <= start + size - 1 {
+                ram.clear(clear_from, start + size - 1)?;
+            }
+
+            Self::relocate(&mut table_process[pos], next_free - start)?;
+            next_free += size;
+            moved += 1;
+        }
+
+        Ok(moved)
+    }
+
+    //Mueve la base del proceso y su contexto guardado, el direccionamiento es relativo a rb salvo pc y sp
+    pub fn relocate(program: &mut Programs, delta: i32) -> Result<(), Errors> {
+        program.pos_start_mem += delta;
+
+        match program.state {
+            State_Process::Ready | State_Process::Running | State_Process::Blocked => {
+                let context = &mut program.context;
+                context.set_rb(Self::shift(context.rb, delta)?)?;
+                context.set_rl(Self::shift(context.rl, delta)?)?;
+                context.set_rx(Self::shift(context.rx, delta)?)?;
+                context.set_rd(Self::shift(context.rd, delta)?)?;
+                context.set_sp(Self::shift(context.sp, delta)?)?;
+                context.psw.set_pc(context.psw.pc + delta)?;
+            }
+            //Nuevo y terminado arman su contexto desde pos_start_mem al correr
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn shift(pal: Palabra, delta: i32) -> Result<Palabra, Errors> {
+        convert_option_result(
+            Palabra::new(&convert_to_string_format_pal(pal.convert() + delta)),
+            "Error al reubicar el registro".to_string(),
+            Interrups::DirInv,
+        )
+    }
+
+    pub fn print_fragmentation(table_process: &Vec<Programs>) {
+        let holes = Self::holes(table_process);
+        let free: i32 = holes.iter().map(|h| h.size).sum();
+        let largest = holes.iter().map(|h| h.size).max().unwrap_or(0);
+
+        //Fragmentación externa: parte de la memoria libre que no esta en el hueco mas grande
+        let fragmentation = if free > 0 {
+            100.0 * (1.0 - largest as f64 / free as f64)
+        } else {
+            0.0
+        };
+
+        let list: Vec<String> = holes
+            .iter()
+            .map(|h| format!("[{}..{}]", h.start, h.start + h.size - 1))
+            .collect();
+        println!("-> Huecos libres: {}", list.join(" "));
+        println!(
+            "-> Memoria libre: {} palabras en {} huecos, hueco mayor: {}, fragmentación externa: {:.2}%",
+            free,
+            holes.len(),
+            largest,
+            fragmentation
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Proceso contiguo listo en [start, start + size) con su contexto en esa posición
+    fn process(pid: i32, start: i32, size: i32) -> Programs {
+        let mut program = Programs::new();
+        program.pid = pid;
+        program.state = State_Process::Ready;
+        program.pos_start_mem = start;
+        program.num_instruccions_with_pila = size - 1;
+        let context = &mut program.context;
+        context.rb = pal(start);
+        context.rd = pal(start);
+        context.rx = pal(start);
+        context.sp = pal(start + size - 1);
+        context.rl = pal(start + size - 1);
+        context.psw.pc = start;
+        program
+    }
+
+    //Huecos [401, 451), [501, 601) y [901, 2001)
+    fn table() -> Vec<Programs> {
+        vec![
+            process(1, 301, 100),
+            process(2, 451, 50),
+            process(3, 601, 300),
+        ]
+    }
+
+    fn allocate(policy: Fit_Policy, size: i32) -> Option<i32> {
+        let mut manager = Memory_Manager::new();
+        manager.policy = policy;
+        manager.allocate(&table(), size)
+    }
+
+    fn pal(value: i32) -> Palabra {
+        Palabra::new(&convert_to_string_format_pal(value)).unwrap()
+    }
+
+    #[test]
+    fn huecos_entre_procesos() {
+        let holes: Vec<(i32, i32)> = Memory_Manager::holes(&table())
+            .iter()
+            .map(|h| (h.start, h.size))
+            .collect();
+        assert_eq!(holes, vec![(401, 50), (501, 100), (901, 1100)]);
+        assert_eq!(Memory_Manager::free_memory(&table()), 1250);
+    }
+
+    #[test]
+    fn first_fit_usa_el_primer_hueco_donde_entra() {
+        assert_eq!(allocate(Fit_Policy::First, 40), Some(401));
+        assert_eq!(allocate(Fit_Policy::First, 60), Some(501));
+        assert_eq!(allocate(Fit_Policy::First, 2000), None);
+    }
+
+    #[test]
+    fn best_fit_usa_el_hueco_mas_chico() {
+        assert_eq!(allocate(Fit_Policy::Best, 40), Some(401));
+        assert_eq!(allocate(Fit_Policy::Best, 90), Some(501));
+        assert_eq!(allocate(Fit_Policy::Best, 200), Some(901));
+    }
+
+    #[test]
+    fn worst_fit_usa_el_hueco_mas_grande() {
+        assert_eq!(allocate(Fit_Policy::Worst, 40), Some(901));
+    }
+
+    #[test]
+    fn next_fit_sigue_desde_la_ultima_asignación() {
+        let mut manager = Memory_Manager::new();
+        manager.policy = Fit_Policy::Next;
+        manager.last_position = 520;
+        assert_eq!(manager.allocate(&table(), 40), Some(520));
+        assert_eq!(manager.last_position, 560);
+
+        //Pasado el ultimo hueco donde entra da la vuelta al primero
+        manager.last_position = USER_MEM_END + 1;
+        assert_eq!(manager.allocate(&table(), 40), Some(401));
+    }
+
+    #[test]
+    fn compactar_mueve_los_procesos_y_deja_fijos_los_que_esperan_e_s() {
+        let ram = Arc::new(Mutex::new(Ram::new()));
+        let mut table = table();
+        table[1].pending_io = 1;
+        table[2].context.psw.pc = 610;
+        {
+            let mut ram = ram.lock().unwrap();
+            ram.writeMemory(601, pal(42)).unwrap();
+            ram.writeMemory(900, pal(7)).unwrap();
+        }
+
+        assert_eq!(Memory_Manager::compact(&mut table, &ram).unwrap(), 1);
+        assert_eq!(table[1].pos_start_mem, 451);
+        assert_eq!(table[2].pos_start_mem, 501);
+        assert_eq!(table[2].context.rb.convert(), 501);
+        assert_eq!(table[2].context.rl.convert(), 800);
+        assert_eq!(table[2].context.psw.pc, 510);
+
+        let ram = ram.lock().unwrap();
+        assert_eq!(ram.readMemory(501).unwrap().convert(), 42);
+        assert_eq!(ram.readMemory(800).unwrap().convert(), 7);
+        assert_eq!(ram.readMemory(900).unwrap().convert(), 0);
+    }
+
+    #[test]
+    fn compactar_no_mueve_procesos_bloqueados() {
+        let ram = Arc::new(Mutex::new(Ram::new()));
+        let mut table = table();
+        table[0].pos_start_mem = 351;
+        table[0].num_instruccions_with_pila = 49;
+        table[0].state = State_Process::Blocked;
+
+        assert_eq!(Memory_Manager::compact(&mut table, &ram).unwrap(), 2);
+        assert_eq!(table[0].pos_start_mem, 351);
+        assert_eq!(table[1].pos_start_mem, 401);
+        assert_eq!(table[2].pos_start_mem, 451);
+    }
+}
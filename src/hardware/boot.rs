@@ -0,0 +1,170 @@
+use std::sync::{Arc, Mutex, mpsc::Sender};
+
+use crate::{
+    Programs,
+    hardware::{
+        architecture::Palabra,
+        disk::{Disk, SECTORES},
+        dma::{Dma_Config, kernel_transfer},
+        interrupts::{External_interrupt, Interrups, KSTACK_START},
+        ram::Ram,
+        registers::Registros,
+    },
+    utils::{
+        Errors, Result_op, convert_option_result, convert_to_string_format_pal,
+        init_context_program,
+    },
+};
+
+//El kernel se guarda en el cilindro 0 pista 0. Los primeros sectores son el encabezado: palabras de
+//código y datos, desplazamiento de la entrada y tamaño de la pila. La imagen sigue desde HEADER_SECTORS
+pub const BOOT_CIL: i8 = 0;
+pub const BOOT_PISTA: i8 = 0;
+const HEADER_SECTORS: i8 = 3;
+
+//Parte del area del sistema donde se carga el kernel, antes estan el vector de interrupciones con
+//sus rutinas y despues la pila del kernel y las tablas de paginas
+pub const KERNEL_START: i32 = 40;
+pub const KERNEL_END: i32 = KSTACK_START - 1;
+
+pub struct Boot;
+
+impl Boot {
+    //Pedido al dma para traer count sectores de la pista de arranque desde sec a pos_men
+    fn boot_transfer(sec: i8, pos_men: i32, count: i32) -> Dma_Config {
+        let mut config = Dma_Config::new();
+        config.cil_acceder = BOOT_CIL;
+        config.pista_acceder = BOOT_PISTA;
+        config.sector_acceder = sec;
+        config.pos_men = pos_men;
+        config.modo = 0;
+        config.count = count;
+        config
+    }
+
+    fn write_number(disk: &mut Disk, value: i32, sec: i8) -> Result_op {
+        disk.write(
+            format!("{}F", convert_to_string_format_pal(value)),
+            BOOT_CIL,
+            BOOT_PISTA,
+            sec,
+        )
+    }
+
+    //El kernel con su pila tiene que entrar en el area del kernel y la imagen en la pista de arranque
+    fn check_size(image_size: i32, stack_size: i32) -> Result_op {
+        if image_size < 1
+            || stack_size < 1
+            || KERNEL_START + image_size + stack_size > KERNEL_END
+            || image_size > (SECTORES - HEADER_SECTORS) as i32
+        {
+            return Err(Errors {
+                msg: format!(
+                    "El kernel no entra en el area {}..{}",
+                    KERNEL_START, KERNEL_END
+                ),
+                cod: Interrups::DirInv,
+            });
+        }
+        Ok(())
+    }
+
+    fn clear_kernel_area(ram: &Arc<Mutex<Ram>>) -> Result_op {
+        let mut ram = ram.lock().unwrap();
+        for dir in KERNEL_START..=KERNEL_END {
+            ram.writeMemory(dir, Palabra::new("00000000").unwrap())?;
+        }
+        Ok(())
+    }
+
+    //Escribe el programa como kernel en la pista de arranque
+    pub fn install(disk: &mut Disk, program: &Programs, words: &[Palabra]) -> Result_op {
+        let image_size = program.stack_base();
+        Self::check_size(image_size, program.stack_size)?;
+        if program.pos_start_program < 1 || program.pos_start_program > program.code_size {
+            return Err(Errors {
+                msg: "La entrada del kernel tiene que estar en el código".to_string(),
+                cod: Interrups::DirInv,
+            });
+        }
+
+        Self::write_number(disk, image_size, 0)?;
+        Self::write_number(disk, program.pos_start_program - 1, 1)?;
+        Self::write_number(disk, program.stack_size, 2)?;
+        for i in 0..image_size {
+            let pal = words
+                .get(i as usize)
+                .copied()
+                .unwrap_or(Palabra::new("00000000").unwrap());
+            disk.write(
+                pal.convert_to_string_disk(),
+                BOOT_CIL,
+                BOOT_PISTA,
+                HEADER_SECTORS + i as i8,
+            )?;
+        }
+        Ok(())
+    }
+
+    //Cargador de arranque: trae el encabezado y despues la imagen con pedidos a la cola del dma, el
+    //encabezado se deja un momento en KERNEL_START. Arma los registros para empezar en modo kernel en
+    //la entrada
+    pub fn load(
+        ram: &Arc<Mutex<Ram>>,
+        sender: &Sender<Dma_Config>,
+        external_int: &Arc<Mutex<External_interrupt>>,
+        disk: &Arc<Mutex<Disk>>,
+    ) -> Result<Registros, Errors> {
+        Self::clear_kernel_area(ram)?;
+        kernel_transfer(
+            sender,
+            external_int,
+            disk,
+            Self::boot_transfer(0, KERNEL_START, HEADER_SECTORS as i32),
+        )?;
+        let (image_size, entry, stack_size) = {
+            let ram = ram.lock().unwrap();
+            (
+                ram.readMemory(KERNEL_START)?.convert(),
+                ram.readMemory(KERNEL_START + 1)?.convert(),
+                ram.readMemory(KERNEL_START + 2)?.convert(),
+            )
+        };
+        if image_size == 0 {
+            return Err(Errors {
+                msg: "No hay un kernel instalado en el disco".to_string(),
+                cod: Interrups::EndIO,
+            });
+        }
+        Self::check_size(image_size, stack_size)?;
+        if entry < 0 || entry >= image_size {
+            return Err(Errors {
+                msg: "La entrada del kernel esta fuera de la imagen".to_string(),
+                cod: Interrups::DirInv,
+            });
+        }
+
+        Self::clear_kernel_area(ram)?;
+        kernel_transfer(
+            sender,
+            external_int,
+            disk,
+            Self::boot_transfer(HEADER_SECTORS, KERNEL_START, image_size),
+        )?;
+
+        //El kernel se ve como un programa en KERNEL_START, con su pila despues de la imagen
+        let mut kernel = Programs::new();
+        kernel.pos_start_mem = KERNEL_START;
+        kernel.pos_start_program = entry + 1;
+        kernel.code_size = image_size;
+        kernel.stack_size = stack_size;
+        kernel.num_instruccions_with_pila = image_size + stack_size;
+        init_context_program(&mut kernel)?;
+
+        let mut registers = kernel.context;
+        registers.psw.set_mode(1)?;
+        //El kernel arranca sin interrupciones, las habilita con hab cuando tiene listo el vector
+        registers.psw.set_inte(0)?;
+        Ok(registers)
+    }
+}
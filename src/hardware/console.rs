@@ -0,0 +1,70 @@
+use std::io::{self, Write};
+
+use crate::{
+    Programs,
+    hardware::interrupts::Interrups,
+    utils::{Errors, Result_op, convert_option_result, convert_result},
+};
+
+//Mayor valor que entra en una palabra con signo
+const MAX_VALUE: i32 = 9999999;
+
+//Consola: cada proceso escribe en su propio flujo de salida y lee numeros de la entrada estandar
+pub struct Console;
+
+impl Console {
+    pub fn write_number(program: &mut Programs, value: i32) {
+        program.output.push_str(&value.to_string());
+    }
+
+    //El valor es el código ascii del caracter, con 10 se pasa a la siguiente linea
+    pub fn write_char(program: &mut Programs, code: i32) -> Result_op {
+        let character = convert_option_result(
+            u8::try_from(code)
+                .ok()
+                .filter(|c| c.is_ascii())
+                .map(char::from),
+            format!("El código {} no es un caracter ascii", code),
+            Interrups::InstInv,
+        )?;
+        program.output.push(character);
+        Ok(())
+    }
+
+    //Bloquea al cpu hasta que se escriba una linea con un numero en la entrada estandar
+    pub fn read_number(name: &str, pid: i32) -> Result<i32, Errors> {
+        print!("-> Entrada para {} (pid {}): ", name, pid);
+        io::stdout().flush();
+
+        let mut buffer = String::new();
+        convert_result(
+            io::stdin().read_line(&mut buffer),
+            "Error al leer la entrada estandar".to_string(),
+            Interrups::EndIO,
+        )?;
+
+        let value = convert_result(
+            buffer.trim().parse::<i32>(),
+            format!("La entrada {} no es un numero", buffer.trim()),
+            Interrups::InstInv,
+        )?;
+        if value.abs() > MAX_VALUE {
+            return Err(Errors {
+                msg: format!("La entrada {} no entra en una palabra", value),
+                cod: Interrups::Overflow,
+            });
+        }
+        Ok(value)
+    }
+
+    //Muestra la salida del proceso aparte de los mensajes del simulador
+    pub fn show(program: &Programs) {
+        println!("--- Salida de {} (pid {}) ---", program.name, program.pid);
+        if program.output.is_empty() {
+            println!("(sin salida)");
+        } else {
+            println!("{}", program.output.trim_end_matches('\n'));
+        }
+        println!("---");
+    }
+}
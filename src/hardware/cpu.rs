@@ -1,23 +1,36 @@
 use std::{
-    sync::{Arc, Mutex, mpsc::Sender},
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender},
+    },
     thread::Thread,
     thread::sleep,
     time::Duration,
 };
 
 use crate::{
-    Mode_Execute,
+    End_Reason, Mode_Execute, Programs, State_Process,
     hardware::{
+        allocator::{Memory_Manager, USER_MEM_START},
         architecture::Palabra,
-        dma::{Dma, Dma_Config},
+        disk::Disk,
+        dma::{DMA_ERR_NONE, Dma, Dma_Config, Dma_Status, State_Dma},
         instructions::Instruction,
-        interrupts::{External_interrupt, Interrups, handle_interrupt},
+        interrupts::{
+            External_interrupt, FRAME_AC, FRAME_PSW, FRAME_RB, FRAME_RD, FRAME_RL, FRAME_RX,
+            FRAME_SIZE, FRAME_SP, Interrups, KSTACK_START, KSTACK_TOP, Semaphore, handle_interrupt,
+            wake_memory_waiters,
+        },
+        mmu::{Mmu, PAGE_SIZE},
         ram::Ram,
         registers::{self, Pws, Registros},
+        scheduler::Scheduler,
+        swap::{Swap_Devices, Swap_Manager},
+        timer::{Timer, Timer_Signal},
     },
     utils::{
         ContinueOrBreak, Errors, Result_op, convert_option_result, convert_result,
-        convert_to_string_format_pal,
+        convert_to_string_format_pal, set_ready,
     },
 };
 #[derive(Debug, Clone, Copy)]
@@ -55,11 +68,6 @@ impl Result_Execute {
 
 pub struct Registers_Cpu_Config {
     pub mode: Mode_Execute,
-    pub rb: Palabra,
-    pub rl: Palabra,
-    pub rx: Palabra,
-    pub sp: Palabra,
-    pub pc: i32,
 }
 #[derive(Debug)]
 pub struct Cpu {
@@ -67,10 +75,41 @@ pub struct Cpu {
     ram: Arc<Mutex<Ram>>,
     pub external_interrupt: Arc<Mutex<External_interrupt>>,
     pub sender_dma: Sender<Dma_Config>,
-    pub clock_interrupt: u32,
+    //Ciclos que se le avisan al hilo del temporizador y su respuesta
+    pub sender_timer: Sender<Timer_Signal>,
+    pub timer_ack: Receiver<()>,
+    //Interrupciones de reloj que recibio el proceso actual en su quantum
+    pub clock_counter: u32,
+    //Quantum del proceso actual según el planificador, None si no se expropia
+    pub current_quantum: Option<u32>,
     pub dma_temp: Dma_Config,
-    pub have_user_program: bool,
+    //Dirección logica que se dio con sdmam, con paginación se revisa que la transferencia sea contigua
+    pub dma_logical: i32,
     pub result_last_program: Result_Execute,
+    pub table_proccess: Arc<Mutex<Vec<Programs>>>,
+    //Pid del programa que esta en el procesador
+    pub current_process: Option<i32>,
+    pub scheduler: Arc<Mutex<Box<dyn Scheduler>>>,
+    //El proceso actual pidio una operación de E/S y se tiene que bloquear al terminar la instrucción
+    pub block_requested: bool,
+    //La interrupción de reloj pidió cambio de contexto, se hace al volver con iret
+    pub switch_requested: bool,
+    //Una llamada al sistema pidió dejar el procesador al volver con iret, listo (yield) o bloqueado (sleep)
+    pub leave_requested: Option<State_Process>,
+    //Traducción de direcciones del proceso actual si esta paginado
+    pub mmu: Mmu,
+    //Pc de la instrucción en curso, para reintentarla despues de un fallo de pagina
+    pc_instruction: i32,
+    //Pc de la ultima instrucción de usuario, es la dirección que se informa si el proceso termina por una falla
+    pub user_pc: i32,
+    //Para traer de swap al proceso elegido por el planificador
+    pub memory_manager: Arc<Mutex<Memory_Manager>>,
+    pub swap_manager: Arc<Mutex<Swap_Manager>>,
+    pub disk: Arc<Mutex<Disk>>,
+    //Temporizador que lanza las interrupciones de reloj, tti le cambia el periodo
+    pub timer: Arc<Mutex<Timer>>,
+    //Semaforos del kernel que usan las llamadas al sistema
+    pub semaphores: Arc<Mutex<Vec<Semaphore>>>,
 }
 
 impl Cpu {
@@ -78,21 +117,60 @@ impl Cpu {
         ram: Arc<Mutex<Ram>>,
         external_interrupt: Arc<Mutex<External_interrupt>>,
         sender_dma: Sender<Dma_Config>,
+        sender_timer: Sender<Timer_Signal>,
+        timer_ack: Receiver<()>,
+        table_proccess: Arc<Mutex<Vec<Programs>>>,
+        scheduler: Arc<Mutex<Box<dyn Scheduler>>>,
+        memory_manager: Arc<Mutex<Memory_Manager>>,
+        swap_manager: Arc<Mutex<Swap_Manager>>,
+        disk: Arc<Mutex<Disk>>,
+        timer: Arc<Mutex<Timer>>,
+        semaphores: Arc<Mutex<Vec<Semaphore>>>,
     ) -> Self {
         Cpu {
             registers: Registros::new(),
-            clock_interrupt: 3,
+            clock_counter: 0,
+            current_quantum: None,
             dma_temp: Dma_Config::new(),
-            have_user_program: false,
+            dma_logical: 0,
             result_last_program: Result_Execute::new(),
             ram,
             external_interrupt,
             sender_dma,
+            sender_timer,
+            timer_ack,
+            table_proccess,
+            current_process: None,
+            scheduler,
+            block_requested: false,
+            switch_requested: false,
+            leave_requested: None,
+            mmu: Mmu::new(),
+            pc_instruction: 0,
+            user_pc: 0,
+            memory_manager,
+            swap_manager,
+            disk,
+            timer,
+            semaphores,
         }
     }
     pub fn run(&mut self) {
-        self.have_user_program = true;
-        while self.have_user_program {
+        while self.have_user_program() || self.wait_for_ready() {
+            self.step();
+            println!("Registers: {:#?}", self.registers);
+            sleep(Duration::from_millis(500));
+        }
+    }
+
+    //Corre el kernel en modo kernel hasta que pasa a modo usuario o le entrega el procesador a un proceso
+    pub fn boot(&mut self, registers: Registros) {
+        self.registers = registers;
+        self.current_process = None;
+        self.current_quantum = None;
+        self.mmu = Mmu::new();
+
+        while self.registers.psw.modo_op == 1 && self.current_process.is_none() {
             self.step();
             println!("Registers: {:#?}", self.registers);
             sleep(Duration::from_millis(500));
@@ -100,8 +178,22 @@ impl Cpu {
     }
 
     pub fn step(&mut self) {
+        let is_user_instruction = self.registers.psw.modo_op == 0;
+        self.pc_instruction = self.registers.psw.pc;
+        if is_user_instruction {
+            self.user_pc = self.pc_instruction;
+        }
         match self.fetch_decode_execute() {
-            Ok(()) => (),
+            Ok(()) => {
+                if is_user_instruction {
+                    self.account_instruction();
+                }
+
+                if self.block_requested {
+                    self.block_requested = false;
+                    self.block_current_process();
+                }
+            }
             Err(E) => match E.cod {
                 Interrups::Overflow => self.external_interrupt.lock().unwrap().int_overflow = true,
                 Interrups::Underflow => {
@@ -118,105 +210,404 @@ impl Cpu {
                     self.external_interrupt.lock().unwrap().int_cod_inte_inv = true
                 }
                 Interrups::EndIO => self.external_interrupt.lock().unwrap().int_io = true,
+                Interrups::IoError => self.external_interrupt.lock().unwrap().int_io_error = true,
+                Interrups::StackOverflow => {
+                    self.external_interrupt.lock().unwrap().int_stack_overflow = true
+                }
+                Interrups::PageFault => {
+                    //La instrucción se vuelve a ejecutar cuando la pagina este cargada
+                    self.registers.psw.pc = self.pc_instruction;
+                    let mut ext = self.external_interrupt.lock().unwrap();
+                    ext.int_page_fault = true;
+                    ext.fault_pid = self.current_process.unwrap_or(-1);
+                    ext.fault_page = self.mmu.fault_page;
+                    ext.fault_pc_page = self.pc_instruction / PAGE_SIZE;
+                }
             },
         }
+        self.tick_timer();
         let result_vec = self.vector_interrupt();
         match result_vec {
             Ok(_) => (),
             Err(err) => {
                 self.result_last_program.result_program = Result_Execute_program::Error;
-                self.have_user_program = false;
+                self.external_interrupt
+                    .lock()
+                    .unwrap()
+                    .clear_process_interrupts();
+                self.finish_current_process(End_Reason::Fault(err.cod, self.user_pc));
             }
         }
     }
 
-    fn vector_interrupt(&mut self) -> Result_op {
-        let (
-            overflow,
-            underflow,
-            dir_inv,
-            inst_inv,
-            io,
-            clock,
-            call_sys,
-            cod_inte_inv,
-            cod_callsys_inv,
-        ) = {
-            let ext = self.external_interrupt.lock().unwrap();
-            (
-                ext.int_overflow,
-                ext.int_underflow,
-                ext.int_dir_inv,
-                ext.int_inst_inv,
-                ext.int_io,
-                ext.int_clock,
-                ext.int_call_sys,
-                ext.int_cod_inte_inv,
-                ext.int_cod_callsys_inv,
-            )
+    //Cada ciclo del cpu es un tick del temporizador: se le avisa a su hilo y se espera a que lo atienda,
+    //asi la interrupción de reloj cae siempre en el mismo ciclo y no depende del reloj real
+    fn tick_timer(&mut self) {
+        if self.sender_timer.send(Timer_Signal::Cycle).is_ok() {
+            self.timer_ack.recv();
+        }
+    }
+
+    //El procesador tiene programa si el proceso actual sigue en ejecución según la tabla de procesos
+    pub fn have_user_program(&self) -> bool {
+        let pid = match self.current_process {
+            Some(pid) => pid,
+            None => return false,
         };
 
-        //Falta salvaguarda de estado para algunas instrucciones en el cambio de contexto
+        let table = self.table_proccess.lock().unwrap();
+        table
+            .iter()
+            .any(|p| p.pid == pid && p.state == State_Process::Running)
+    }
+
+    fn position_current_process(&self, table: &Vec<Programs>) -> Option<usize> {
+        let pid = self.current_process?;
+        table.iter().position(|p| p.pid == pid)
+    }
 
-        if overflow {
-            self.registers.psw.set_mode(1)?;
-            self.registers.psw.pc = 8;
-            return Ok(());
+    //Suma la instrucción al proceso actual y el tiempo de espera a los que estan listos
+    fn account_instruction(&mut self) {
+        let mut table = self.table_proccess.lock().unwrap();
+        let current = self.current_process;
+
+        for program in table.iter_mut() {
+            if Some(program.pid) == current {
+                program.instructions_executed += 1;
+            } else if program.state == State_Process::Ready {
+                program.wait_time += 1;
+            }
         }
+    }
 
-        if underflow {
-            self.registers.psw.set_mode(1)?;
+    //Carga en el procesador el proceso listo que elija el planificador
+    pub fn dispatch_next(&mut self) -> bool {
+        let mut scheduler = self.scheduler.lock().unwrap();
+        let mut table = self.table_proccess.lock().unwrap();
 
-            self.registers.psw.pc = 7;
-            return Ok(());
+        scheduler.dispatching(&mut table);
+        let mut selected = scheduler.select(&table);
+        while let Some(pos) = selected {
+            if table[pos].swap_sector < 0 {
+                break;
+            }
+            //El elegido queda bloqueado mientras el dma lo trae de swap. Si no hay lugar espera bloqueado a
+            //que otro proceso termine o libere memoria, y si no queda nadie que la libere no puede seguir
+            let others_active = table.iter().enumerate().any(|(i, p)| {
+                i != pos
+                    && p.swap_sector < 0
+                    && matches!(
+                        p.state,
+                        State_Process::Ready | State_Process::Running | State_Process::Blocked
+                    )
+            });
+            match self.swap_in(&mut table, pos) {
+                Ok(true) => (),
+                Ok(false) if others_active => table[pos].state = State_Process::Blocked,
+                result => {
+                    let msg = match result {
+                        Err(err) => err.msg,
+                        _ => "no hay lugar en memoria".to_string(),
+                    };
+                    println!(
+                        "Error al traer de swap el proceso {}: {}",
+                        table[pos].pid, msg
+                    );
+                    table[pos].state = State_Process::Terminated;
+                    table[pos].exit_status = -1;
+                    table[pos].end_reason =
+                        End_Reason::Fault(Interrups::EndIO, table[pos].context.psw.pc);
+                }
+            }
+            selected = scheduler.select(&table);
+        }
+
+        match selected {
+            Some(pos) => {
+                table[pos].state = State_Process::Running;
+                table[pos].dispatches += 1;
+                self.registers = table[pos].context;
+                self.dma_temp = table[pos].dma_temp;
+                self.dma_logical = table[pos].dma_logical;
+                self.mmu.ptbr = table[pos].ptbr;
+                self.mmu.ptlr = table[pos].pages;
+                self.current_process = Some(table[pos].pid);
+                //El quantum se mide en interrupciones del temporizador
+                self.current_quantum = scheduler.quantum(&table[pos], 1);
+                self.clock_counter = 0;
+                true
+            }
+            None => {
+                self.current_process = None;
+                false
+            }
         }
-        if dir_inv {
-            self.registers.psw.set_mode(1)?;
+    }
+
+    //Pide traer de swap al proceso elegido, sacando a otros si hace falta lugar. Devuelve false si por
+    //ahora no hay lugar
+    fn swap_in(&self, table: &mut Vec<Programs>, pos: usize) -> Result<bool, Errors> {
+        let mut memory_manager = self.memory_manager.lock().unwrap();
+        let mut swap_manager = self.swap_manager.lock().unwrap();
+        let devices = Swap_Devices {
+            sender: &self.sender_dma,
+            external_int: &self.external_interrupt,
+            disk: &self.disk,
+        };
 
-            self.registers.psw.pc = 6;
-            return Ok(());
+        let size = Memory_Manager::size_program(&table[pos]);
+        let pid = table[pos].pid;
+        let dir = match swap_manager.make_room(
+            &mut memory_manager,
+            table,
+            &self.ram,
+            &devices,
+            size,
+            pid,
+        )? {
+            Some(dir) => dir,
+            None => return Ok(false),
+        };
+        swap_manager.swap_in(&mut table[pos], dir, &devices)?;
+        Ok(true)
+    }
+
+    //Guarda en el pcb los registros del proceso, junto con lo que dejo programado en el dma para que
+    //otro proceso no se lo pise antes de su sdmaon
+    fn save_process_context(&self, program: &mut Programs) {
+        program.context = self.registers;
+        program.dma_temp = self.dma_temp;
+        program.dma_logical = self.dma_logical;
+    }
+
+    //Deja el contexto del proceso en su pcb entre pasos del debugger, asi la terminal lo puede modificar
+    pub fn park_context(&mut self) {
+        let mut table = self.table_proccess.lock().unwrap();
+        if let Some(pos) = self.position_current_process(&table) {
+            self.save_process_context(&mut table[pos]);
         }
-        if inst_inv {
-            self.registers.psw.set_mode(1)?;
+    }
 
-            self.registers.psw.pc = 5;
-            return Ok(());
+    //Vuelve a tomar el contexto del pcb por si la terminal lo modifico (por ejemplo al compactar)
+    pub fn unpark_context(&mut self) {
+        let table = self.table_proccess.lock().unwrap();
+        if let Some(pos) = self.position_current_process(&table) {
+            self.registers = table[pos].context;
+            self.dma_temp = table[pos].dma_temp;
+            self.dma_logical = table[pos].dma_logical;
+            self.mmu.ptbr = table[pos].ptbr;
+            self.mmu.ptlr = table[pos].pages;
         }
-        if io {
-            self.save_context()?;
+    }
 
-            self.registers.psw.set_mode(1)?;
+    //Si no hay procesos listos pero hay bloqueados, el cpu queda ocioso esperando el fin de una E/S
+    pub fn wait_for_ready(&mut self) -> bool {
+        loop {
+            if self.dispatch_next() {
+                return true;
+            }
 
-            self.registers.psw.pc = 4;
-            return Ok(());
+            //Los que esperan un semaforo solo los despierta otro proceso, si quedan solo ellos no hay quien
+            let (have_blocked, have_waiting) = {
+                let table = self.table_proccess.lock().unwrap();
+                let blocked = table.iter().filter(|p| p.state == State_Process::Blocked);
+                (
+                    blocked.clone().any(|p| p.sem_wait.is_none()),
+                    blocked.clone().any(|p| p.sem_wait.is_some()),
+                )
+            };
+            if !have_blocked {
+                if have_waiting {
+                    println!(
+                        "-> Los procesos bloqueados esperan semaforos y no queda quien los despierte"
+                    );
+                }
+                return false;
+            }
+
+            //Sin proceso el cpu da un ciclo vacio, que tambien avanza el temporizador
+            sleep(Duration::from_millis(100));
+            self.tick_timer();
+
+            //Sin proceso en el procesador no hay contexto que salvar, se atiende directo
+            let (io, io_error, clock) = {
+                let ext = self.external_interrupt.lock().unwrap();
+                (ext.int_io, ext.int_io_error, ext.int_clock)
+            };
+            if io_error {
+                handle_interrupt(
+                    &mut self.registers,
+                    Interrups::IoError,
+                    Arc::clone(&self.ram),
+                    Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
+                );
+            }
+            if io {
+                handle_interrupt(
+                    &mut self.registers,
+                    Interrups::EndIO,
+                    Arc::clone(&self.ram),
+                    Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
+                );
+            }
+            if clock {
+                handle_interrupt(
+                    &mut self.registers,
+                    Interrups::Clock,
+                    Arc::clone(&self.ram),
+                    Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
+                );
+            }
         }
-        if clock {
-            self.save_context()?;
+    }
 
-            self.registers.psw.set_mode(1)?;
+    //El proceso actual espera su E/S o dormido, guarda su contexto y le da el procesador al siguiente
+    pub fn block_current_process(&mut self) {
+        {
+            let mut table = self.table_proccess.lock().unwrap();
+            if let Some(pos) = self.position_current_process(&table) {
+                self.save_process_context(&mut table[pos]);
+                table[pos].state = State_Process::Blocked;
+            }
+        }
 
-            self.registers.psw.pc = 3;
-            return Ok(());
+        self.dispatch_next();
+    }
+
+    //El proceso actual deja el procesador por su cuenta, sin gastar el quantum
+    fn leave_current_process(&mut self, state: State_Process) {
+        if state == State_Process::Blocked {
+            self.block_current_process();
+            return;
+        }
+
+        {
+            let mut table = self.table_proccess.lock().unwrap();
+            if let Some(pos) = self.position_current_process(&table) {
+                self.save_process_context(&mut table[pos]);
+                if table[pos].state == State_Process::Running {
+                    set_ready(&mut table, pos);
+                }
+            }
         }
-        if call_sys {
-            self.save_context()?;
 
-            self.registers.psw.set_mode(1)?;
+        self.dispatch_next();
+    }
 
-            self.registers.psw.pc = 2;
-            return Ok(());
+    //Se vencio el quantum: guarda el contexto del proceso actual, lo manda a listo y da el procesador al siguiente
+    pub fn context_switch(&mut self) {
+        {
+            let mut scheduler = self.scheduler.lock().unwrap();
+            let mut table = self.table_proccess.lock().unwrap();
+            if let Some(pos) = self.position_current_process(&table) {
+                self.save_process_context(&mut table[pos]);
+                if table[pos].state == State_Process::Running {
+                    scheduler.quantum_expired(&mut table[pos]);
+                    set_ready(&mut table, pos);
+                }
+            }
         }
-        if cod_inte_inv {
-            self.registers.psw.set_mode(1)?;
-            self.registers.psw.pc = 1;
-            return Ok(());
+
+        self.dispatch_next();
+    }
+
+    //Termina el proceso actual y continua con el siguiente. Con exit el estado ya lo dejo la llamada
+    //al sistema, si no es -1 por una falla o -9 si lo mataron
+    pub fn finish_current_process(&mut self, reason: End_Reason) {
+        {
+            let mut table = self.table_proccess.lock().unwrap();
+            if let Some(pos) = self.position_current_process(&table) {
+                table[pos].state = State_Process::Terminated;
+                table[pos].end_reason = reason;
+                table[pos].exit_status = match reason {
+                    End_Reason::Exit => table[pos].exit_status,
+                    End_Reason::Killed => -9,
+                    _ => -1,
+                };
+                println!(
+                    "-> Termino el proceso {} (pid {}) con estado {}, {}",
+                    table[pos].name,
+                    table[pos].pid,
+                    table[pos].exit_status,
+                    reason.describe()
+                );
+            }
+            wake_memory_waiters(&mut table);
         }
-        if cod_callsys_inv {
-            self.registers.psw.set_mode(1)?;
-            self.registers.psw.pc = 0;
-            return Ok(());
+
+        self.dispatch_next();
+    }
+
+    //En modo usuario las direcciones de un proceso paginado son logicas y pasan por la mmu
+    fn translate(&mut self, dir: i32) -> Result<i32, Errors> {
+        if self.registers.psw.modo_op == 1 || !self.mmu.enabled() {
+            return Ok(dir);
         }
 
+        let state_mem = self.ram.lock();
+        let state_mem = convert_result(
+            state_mem,
+            "Error con la sincronización del bus".to_string(),
+            Interrups::DirInv,
+        )?;
+        self.mmu.translate(&state_mem, dir)
+    }
+
+    //Los programas sin .Codigo en el encabezado pueden escribir en todo su espacio como antes
+    fn code_protected(&self) -> bool {
+        let table = self.table_proccess.lock().unwrap();
+        self.current_process
+            .and_then(|pid| table.iter().find(|p| p.pid == pid))
+            .is_some_and(|p| p.code_protected)
+    }
+
+    //Dirección de la rutina de la interrupción, guardada en su entrada del vector
+    fn vector_address(&self, vector: i32) -> Result<i32, Errors> {
+        let dir = self.ram.lock().unwrap().readMemory(vector)?.convert();
+        if !(0..USER_MEM_START).contains(&dir) {
+            return Err(Errors {
+                msg: format!(
+                    "La entrada {} del vector no apunta al area del sistema",
+                    vector
+                ),
+                cod: Interrups::CodIntInv,
+            });
+        }
+        Ok(dir)
+    }
+
+    fn vector_interrupt(&mut self) -> Result_op {
+        //El controlador entrega la interrupción pendiente de mayor prioridad, las enmascarables
+        //esperan mientras inte este en 0 o su linea este enmascarada
+        let line = {
+            let ext = self.external_interrupt.lock().unwrap();
+            ext.next_pending(self.registers.psw.inte == 1)
+        };
+        let line = match line {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+
+        //Todas las interrupciones guardan su marco, asi una falla de la pila del usuario tambien se atiende
+        self.save_context()?;
+
+        //La rutina corre en modo kernel y sin interrupciones enmascarables hasta que vuelva con iret.
+        //La entrada del vector tiene la dirección de la rutina que atiende la interrupción
+        self.registers.psw.set_mode(1)?;
+        self.registers.psw.set_inte(0)?;
+        self.registers.psw.pc = self.vector_address(line as i32)?;
         Ok(())
     }
 
@@ -232,6 +623,7 @@ impl Cpu {
     }
 
     fn fetch(&mut self) -> Result_op {
+        let pc_fisico = self.translate(self.registers.psw.pc)?;
         let state_mem_result = self.ram.lock();
         let mut state_mem = match state_mem_result {
             Ok(val) => val,
@@ -256,7 +648,7 @@ impl Cpu {
         )?;
 
         if self.registers.psw.modo_op != 1 {
-            if pos_mem_palabra < self.registers.rb || pos_mem_palabra >= self.registers.rx {
+            if pos_mem_palabra < self.registers.rb || pos_mem_palabra >= self.registers.rd {
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from("Fuera de los límites de memoria"));
                 return Err(Errors {
@@ -266,8 +658,12 @@ impl Cpu {
             }
         }
 
-        self.registers.set_mar(pos_mem_palabra)?;
-        self.registers.mdr = state_mem.readMemory(self.registers.psw.pc)?;
+        self.registers.set_mar(convert_option_result(
+            Palabra::new(&convert_to_string_format_pal(pc_fisico)),
+            "Error al transformar palabra pc".to_string(),
+            Interrups::InstInv,
+        )?)?;
+        self.registers.mdr = state_mem.readMemory(pc_fisico)?;
         self.registers.ir = Instruction::new(self.registers.mdr);
         self.registers.psw.pc += 1;
         Ok(())
@@ -343,24 +739,72 @@ impl Cpu {
             31 => self.sdmaio()?,
             32 => self.sdmam()?,
             33 => self.sdmaon()?,
+            34 => self.sdmacn()?,
+            35 => self.ldmae()?,
+            36 => self.iret()?,
+            37 => self.mski(true)?,
+            38 => self.mski(false)?,
+            //Las rutinas de servicio de las interrupciones solo corren en modo kernel
+            88..=99 if self.registers.psw.modo_op == 0 => {
+                self.result_last_program.result_instruction = Result_Instruction::String(
+                    String::from("Rutina de interrupción en modo usuario"),
+                );
+                return Err(Errors {
+                    msg: "Falta de privilegios, se necesita modo kernel".to_string(),
+                    cod: Interrups::InstInv,
+                });
+            }
+            88 => {
+                let response_handle = handle_interrupt(
+                    &mut self.registers,
+                    Interrups::IoError,
+                    Arc::clone(&self.ram),
+                    Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
+                );
+                self.result_last_program.result_instruction =
+                    Result_Instruction::String(String::from(format!("Error de I/O")));
+
+                match response_handle {
+                    ContinueOrBreak::Break => {
+                        self.chmod();
+                        self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::IoError,
+                            self.user_pc,
+                        ));
+                    }
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
+                }
+            }
             90 => {
                 let response_handle = handle_interrupt(
                     &mut self.registers,
                     Interrups::CodCallSysInv,
                     Arc::clone(&self.ram),
                     Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
                 );
-                self.chmod();
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from(format!("Llamada a sistema invalida")));
                 match response_handle {
                     ContinueOrBreak::Break => {
-                        self.have_user_program = false;
+                        self.chmod();
                         self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::CodCallSysInv,
+                            self.user_pc,
+                        ));
                     }
-                    ContinueOrBreak::Continue => {
-                        self.restore_context();
-                    }
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
                 }
             }
             91 => {
@@ -369,8 +813,11 @@ impl Cpu {
                     Interrups::CodIntInv,
                     Arc::clone(&self.ram),
                     Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
                 );
-                self.chmod();
 
                 self.result_last_program.result_instruction = Result_Instruction::String(
                     String::from(format!("Codigo de interrupcion invalida")),
@@ -378,12 +825,15 @@ impl Cpu {
 
                 match response_handle {
                     ContinueOrBreak::Break => {
-                        self.have_user_program = false;
+                        self.chmod();
                         self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::CodIntInv,
+                            self.user_pc,
+                        ));
                     }
-                    ContinueOrBreak::Continue => {
-                        self.restore_context();
-                    }
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
                 }
             }
             92 => {
@@ -392,8 +842,11 @@ impl Cpu {
                     Interrups::CallSys,
                     Arc::clone(&self.ram),
                     Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
                 );
-                self.chmod();
 
                 if self.registers.ac.convert() == 1 {
                     self.result_last_program.result_instruction = Result_Instruction::String(
@@ -408,12 +861,23 @@ impl Cpu {
                 }
 
                 match response_handle {
-                    ContinueOrBreak::Break => {
-                        self.have_user_program = false;
+                    ContinueOrBreak::Exit => {
+                        self.chmod();
+                        self.finish_current_process(End_Reason::Exit);
                     }
-                    ContinueOrBreak::Continue => {
-                        self.restore_context();
+                    //Si la llamada no pudo terminar bien el proceso no sale como exit normal
+                    ContinueOrBreak::Break => {
+                        self.chmod();
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::CallSys,
+                            self.user_pc,
+                        ));
                     }
+                    //La rutina sigue con iret, que restaura el contexto
+                    ContinueOrBreak::Continue => (),
+                    //El proceso deja el procesador despues de iret
+                    ContinueOrBreak::Yield => self.leave_requested = Some(State_Process::Ready),
+                    ContinueOrBreak::Block => self.leave_requested = Some(State_Process::Blocked),
                 }
             }
             93 => {
@@ -422,19 +886,35 @@ impl Cpu {
                     Interrups::Clock,
                     Arc::clone(&self.ram),
                     Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
                 );
-                self.chmod();
 
-                self.result_last_program.result_instruction =
-                    Result_Instruction::String(String::from(format!("Llamada a sistema invalida")));
+                self.result_last_program.result_instruction = Result_Instruction::String(
+                    String::from(format!("Interrupción de reloj, cambio de contexto")),
+                );
                 match response_handle {
                     ContinueOrBreak::Break => {
-                        self.have_user_program = false;
+                        self.chmod();
                         self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::Clock,
+                            self.user_pc,
+                        ));
                     }
+                    //Al cumplirse el quantum el cambio de contexto se hace en iret, despues de restaurar el contexto
                     ContinueOrBreak::Continue => {
-                        self.restore_context();
+                        if let Some(quantum) = self.current_quantum {
+                            self.clock_counter += 1;
+                            if self.clock_counter >= quantum {
+                                self.clock_counter = 0;
+                                self.switch_requested = true;
+                            }
+                        }
                     }
+                    _ => (),
                 }
             }
             94 => {
@@ -443,19 +923,25 @@ impl Cpu {
                     Interrups::EndIO,
                     Arc::clone(&self.ram),
                     Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
                 );
-                self.chmod();
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from(format!("Termino I/O")));
 
                 match response_handle {
                     ContinueOrBreak::Break => {
-                        self.have_user_program = false;
+                        self.chmod();
                         self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::EndIO,
+                            self.user_pc,
+                        ));
                     }
-                    ContinueOrBreak::Continue => {
-                        self.restore_context();
-                    }
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
                 }
             }
             95 => {
@@ -464,19 +950,25 @@ impl Cpu {
                     Interrups::InstInv,
                     Arc::clone(&self.ram),
                     Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
                 );
-                self.chmod();
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from(format!("Instrucción Inválida")));
 
                 match response_handle {
                     ContinueOrBreak::Break => {
-                        self.have_user_program = false;
+                        self.chmod();
                         self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::InstInv,
+                            self.user_pc,
+                        ));
                     }
-                    ContinueOrBreak::Continue => {
-                        self.restore_context();
-                    }
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
                 }
             }
             96 => {
@@ -485,19 +977,25 @@ impl Cpu {
                     Interrups::DirInv,
                     Arc::clone(&self.ram),
                     Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
                 );
-                self.chmod();
 
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from(format!("Direccionamiento Inválido")));
                 match response_handle {
                     ContinueOrBreak::Break => {
-                        self.have_user_program = false;
+                        self.chmod();
                         self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::DirInv,
+                            self.user_pc,
+                        ));
                     }
-                    ContinueOrBreak::Continue => {
-                        self.restore_context();
-                    }
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
                 }
             }
             97 => {
@@ -506,19 +1004,25 @@ impl Cpu {
                     Interrups::Underflow,
                     Arc::clone(&self.ram),
                     Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
                 );
-                self.chmod();
 
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from(format!("Underflow")));
                 match response_handle {
                     ContinueOrBreak::Break => {
-                        self.have_user_program = false;
+                        self.chmod();
                         self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::Underflow,
+                            self.user_pc,
+                        ));
                     }
-                    ContinueOrBreak::Continue => {
-                        self.restore_context();
-                    }
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
                 }
             }
             98 => {
@@ -527,19 +1031,79 @@ impl Cpu {
                     Interrups::Overflow,
                     Arc::clone(&self.ram),
                     Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
                 );
-                self.chmod();
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from(format!("Overflow")));
 
                 match response_handle {
                     ContinueOrBreak::Break => {
-                        self.have_user_program = false;
+                        self.chmod();
                         self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::Overflow,
+                            self.user_pc,
+                        ));
                     }
-                    ContinueOrBreak::Continue => {
-                        self.restore_context();
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
+                }
+            }
+            89 => {
+                let response_handle = handle_interrupt(
+                    &mut self.registers,
+                    Interrups::StackOverflow,
+                    Arc::clone(&self.ram),
+                    Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
+                );
+                self.result_last_program.result_instruction =
+                    Result_Instruction::String(String::from(format!("Desbordamiento de pila")));
+
+                match response_handle {
+                    ContinueOrBreak::Break => {
+                        self.chmod();
+                        self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::StackOverflow,
+                            self.user_pc,
+                        ));
+                    }
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
+                }
+            }
+            99 => {
+                let response_handle = handle_interrupt(
+                    &mut self.registers,
+                    Interrups::PageFault,
+                    Arc::clone(&self.ram),
+                    Arc::clone(&self.external_interrupt),
+                    Arc::clone(&self.table_proccess),
+                    Arc::clone(&self.disk),
+                    Arc::clone(&self.timer),
+                    Arc::clone(&self.semaphores),
+                );
+                self.result_last_program.result_instruction =
+                    Result_Instruction::String(String::from(format!("Fallo de pagina")));
+
+                match response_handle {
+                    ContinueOrBreak::Break => {
+                        self.chmod();
+                        self.result_last_program.result_program = Result_Execute_program::Error;
+                        self.finish_current_process(End_Reason::Fault(
+                            Interrups::PageFault,
+                            self.user_pc,
+                        ));
                     }
+                    //La rutina sigue con iret, que restaura el contexto
+                    _ => (),
                 }
             }
             _ => {
@@ -554,51 +1118,85 @@ impl Cpu {
         Ok(())
     }
 
+    //Guarda el marco del proceso interrumpido en la pila del kernel. Si ya se esta en una rutina el
+    //marco queda debajo del suyo, si no se empieza en el tope. La pila del usuario no se toca
     pub fn save_context(&mut self) -> Result_op {
-        let ac_temp = self.registers.ac;
-        //Guardo Ac en pila
-        self.psh(true)?;
-
-        //Guardo rb en la pila
-        self.registers.ac = self.registers.rb;
-        self.psh(true)?;
-
-        //Guardo rl en la pila
-        self.registers.ac = self.registers.rl;
-        self.psh(true)?;
-
-        //Guardo rx en la pila
-        self.registers.ac = self.registers.rx;
-        self.psh(true)?;
+        let sp = self.registers.sp.convert();
+        let mut ksp =
+            if self.registers.psw.modo_op == 1 && (KSTACK_START..=KSTACK_TOP).contains(&sp) {
+                sp
+            } else {
+                KSTACK_TOP
+            };
+        if ksp - FRAME_SIZE < KSTACK_START {
+            return Err(Errors {
+                msg: "Desbordamiento de la pila del kernel".to_string(),
+                cod: Interrups::StackOverflow,
+            });
+        }
 
-        //Guardo psw en la pila
-        self.registers.ac = Palabra::new(&self.registers.psw.convert_to_palabra()).unwrap();
-        self.psh(true)?;
+        //Se apila del ultimo al primero para que el psw quede en el tope
+        let frame = [
+            self.registers.ac,
+            self.registers.rb,
+            self.registers.rl,
+            self.registers.rx,
+            self.registers.rd,
+            self.registers.sp,
+            Palabra::new(&self.registers.psw.convert_to_palabra()).unwrap(),
+        ];
+        {
+            let state_mem = self.ram.lock();
+            let mut state_mem = convert_result(
+                state_mem,
+                "Error con la sincronización del bus".to_string(),
+                Interrups::DirInv,
+            )?;
+            for pal in frame {
+                ksp -= 1;
+                state_mem.writeMemory(ksp, pal)?;
+            }
+        }
 
-        self.registers.ac = ac_temp;
+        //La rutina usa la pila del kernel con sus limites
+        self.registers.sp = Palabra::new(&convert_to_string_format_pal(ksp)).unwrap();
+        self.registers.rx = Palabra::new(&convert_to_string_format_pal(KSTACK_START)).unwrap();
+        self.registers.rl = Palabra::new(&convert_to_string_format_pal(KSTACK_TOP)).unwrap();
 
         Ok(())
     }
 
+    //Saca el marco del tope de la pila del kernel y vuelve a los registros del interrumpido
     pub fn restore_context(&mut self) -> Result_op {
-        //Restauro psw de la pila
-        self.pop(true)?;
-        self.registers
-            .psw
-            .convert_to_psw_by_palabra(self.registers.ac);
+        let ksp = self.registers.sp.convert();
+        if ksp < KSTACK_START || ksp + FRAME_SIZE > KSTACK_TOP {
+            return Err(Errors {
+                msg: "No hay un marco de interrupción en la pila del kernel".to_string(),
+                cod: Interrups::InstInv,
+            });
+        }
 
-        //Restaura rx de la pila
-        self.pop(true)?;
-        self.registers.set_rx(self.registers.ac);
+        let frame = {
+            let state_mem = self.ram.lock();
+            let state_mem = convert_result(
+                state_mem,
+                "Error con la sincronización del bus".to_string(),
+                Interrups::DirInv,
+            )?;
+            (0..FRAME_SIZE)
+                .map(|offset| state_mem.readMemory(ksp + offset))
+                .collect::<Result<Vec<Palabra>, Errors>>()?
+        };
 
-        //Restaura rl de la pila
-        self.pop(true)?;
-        self.registers.set_rl(self.registers.ac);
-        //Restaura rb de la pila
-        self.pop(true)?;
-        self.registers.set_rb(self.registers.ac);
-        //Restaura Ac de la pila
-        self.pop(true)?;
+        self.registers
+            .psw
+            .convert_to_psw_by_palabra(frame[FRAME_PSW as usize])?;
+        self.registers.set_sp(frame[FRAME_SP as usize])?;
+        self.registers.set_rd(frame[FRAME_RD as usize])?;
+        self.registers.set_rx(frame[FRAME_RX as usize])?;
+        self.registers.set_rl(frame[FRAME_RL as usize])?;
+        self.registers.set_rb(frame[FRAME_RB as usize])?;
+        self.registers.ac = frame[FRAME_AC as usize];
 
         Ok(())
     }
@@ -619,7 +1217,7 @@ impl Cpu {
                 });
             }
         } else {
-            if dir_num >= self.registers.rx.convert() {
+            if dir_num >= self.registers.rx.convert() || dir_num < self.registers.rb.convert() {
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from("Direccionamiento Invalido "));
                 return Err(Errors {
@@ -629,6 +1227,7 @@ impl Cpu {
             }
         }
 
+        let dir_num = self.translate(dir_num)?;
         let dir = convert_option_result(
             Palabra::new(&convert_to_string_format_pal(dir_num)),
             "Dir invalida".to_string(),
@@ -674,7 +1273,7 @@ impl Cpu {
                 });
             }
         } else {
-            if dir_num >= self.registers.rx.convert() {
+            if dir_num >= self.registers.rx.convert() || dir_num < self.registers.rb.convert() {
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from("Direccionamiento Invalido "));
                 return Err(Errors {
@@ -682,8 +1281,18 @@ impl Cpu {
                     cod: Interrups::DirInv,
                 });
             }
+            //El segmento de código es de solo lectura
+            if dir_num < self.registers.rd.convert() && self.code_protected() {
+                self.result_last_program.result_instruction =
+                    Result_Instruction::String(String::from("Escritura en el segmento de código"));
+                return Err(Errors {
+                    msg: "Escritura en el segmento de código".to_string(),
+                    cod: Interrups::DirInv,
+                });
+            }
         }
 
+        let dir_num = self.translate(dir_num)?;
         let dir = convert_option_result(
             Palabra::new(&convert_to_string_format_pal(dir_num)),
             "Dir invalida".to_string(),
@@ -711,7 +1320,7 @@ impl Cpu {
                 });
             }
         } else {
-            if dir_num >= self.registers.rx.convert() {
+            if dir_num >= self.registers.rx.convert() || dir_num < self.registers.rb.convert() {
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from("Direccionamiento Invalido "));
                 return Err(Errors {
@@ -719,8 +1328,18 @@ impl Cpu {
                     cod: Interrups::DirInv,
                 });
             }
+            //El segmento de código es de solo lectura
+            if dir_num < self.registers.rd.convert() && self.code_protected() {
+                self.result_last_program.result_instruction =
+                    Result_Instruction::String(String::from("Escritura en el segmento de código"));
+                return Err(Errors {
+                    msg: "Escritura en el segmento de código".to_string(),
+                    cod: Interrups::DirInv,
+                });
+            }
         }
 
+        let dir_num = self.translate(dir_num)?;
         let dir = convert_option_result(
             Palabra::new(&convert_to_string_format_pal(dir_num)),
             "Dir invalida".to_string(),
@@ -763,7 +1382,7 @@ impl Cpu {
                 });
             }
         } else {
-            if dir_num >= self.registers.rx.convert() {
+            if dir_num >= self.registers.rx.convert() || dir_num < self.registers.rb.convert() {
                 self.result_last_program.result_instruction =
                     Result_Instruction::String(String::from("Direccionamiento Invalido "));
                 return Err(Errors {
@@ -772,6 +1391,7 @@ impl Cpu {
                 });
             }
         }
+        let dir_num = self.translate(dir_num)?;
         let dir = convert_option_result(
             Palabra::new(&convert_to_string_format_pal(dir_num)),
             "Dir invalida".to_string(),
@@ -937,6 +1557,7 @@ impl Cpu {
     }
 
     pub fn jmpe(&mut self) -> Result_op {
+        let sp_fisico = self.translate(self.registers.sp.convert())?;
         let state_mem = self.ram.lock();
         let mut state_mem = convert_result(
             state_mem,
@@ -944,7 +1565,7 @@ impl Cpu {
             Interrups::DirInv,
         )?;
 
-        let memory_readed = state_mem.readMemory(self.registers.sp.convert())?;
+        let memory_readed = state_mem.readMemory(sp_fisico)?;
 
         if self.registers.ac == memory_readed {
             self.registers.psw.set_pc(self.registers.mdr.convert())?;
@@ -961,6 +1582,7 @@ impl Cpu {
         Ok(())
     }
     pub fn jmpne(&mut self) -> Result_op {
+        let sp_fisico = self.translate(self.registers.sp.convert())?;
         let state_mem = self.ram.lock();
         let mut state_mem = convert_result(
             state_mem,
@@ -968,7 +1590,7 @@ impl Cpu {
             Interrups::DirInv,
         )?;
 
-        let memory_readed = state_mem.readMemory(self.registers.sp.convert())?;
+        let memory_readed = state_mem.readMemory(sp_fisico)?;
 
         if self.registers.ac != memory_readed {
             self.registers.psw.set_pc(self.registers.mdr.convert())?;
@@ -984,6 +1606,7 @@ impl Cpu {
         Ok(())
     }
     pub fn jmplt(&mut self) -> Result_op {
+        let sp_fisico = self.translate(self.registers.sp.convert())?;
         let state_mem = self.ram.lock();
         let mut state_mem = convert_result(
             state_mem,
@@ -991,7 +1614,7 @@ impl Cpu {
             Interrups::DirInv,
         )?;
 
-        let memory_readed = state_mem.readMemory(self.registers.sp.convert())?;
+        let memory_readed = state_mem.readMemory(sp_fisico)?;
 
         if self.registers.ac < memory_readed {
             self.registers.psw.set_pc(self.registers.mdr.convert())?;
@@ -1005,6 +1628,7 @@ impl Cpu {
         Ok(())
     }
     pub fn jmplgt(&mut self) -> Result_op {
+        let sp_fisico = self.translate(self.registers.sp.convert())?;
         let state_mem = self.ram.lock();
         let mut state_mem = convert_result(
             state_mem,
@@ -1012,7 +1636,7 @@ impl Cpu {
             Interrups::DirInv,
         )?;
 
-        let memory_readed = state_mem.readMemory(self.registers.sp.convert())?;
+        let memory_readed = state_mem.readMemory(sp_fisico)?;
 
         if self.registers.ac > memory_readed {
             self.registers.psw.set_pc(self.registers.mdr.convert())?;
@@ -1041,12 +1665,15 @@ impl Cpu {
         let new_sp = (self.registers.sp - Palabra::new("00000001").unwrap())?;
 
         if (new_sp < self.registers.rx) {
+            self.result_last_program.result_instruction =
+                Result_Instruction::String(String::from("Desbordamiento de pila"));
             return Err(Errors {
                 msg: "Stack Overflow".to_string(),
-                cod: Interrups::InstInv,
+                cod: Interrups::StackOverflow,
             });
         }
 
+        let sp_fisico = self.translate(new_sp.convert())?;
         let state_mem = self.ram.lock();
         let mut state_mem = convert_result(
             state_mem,
@@ -1054,7 +1681,7 @@ impl Cpu {
             Interrups::DirInv,
         )?;
 
-        state_mem.writeMemory(new_sp.convert(), self.registers.ac)?;
+        state_mem.writeMemory(sp_fisico, self.registers.ac)?;
 
         self.registers.sp = new_sp;
         if !is_save_context {
@@ -1069,6 +1696,7 @@ impl Cpu {
     pub fn pop(&mut self, is_save_context: bool) -> Result_op {
         let new_sp = (self.registers.sp + Palabra::new("00000001").unwrap())?;
 
+        let sp_fisico = self.translate(self.registers.sp.convert())?;
         let state_mem = self.ram.lock();
         let mut state_mem = convert_result(
             state_mem,
@@ -1076,7 +1704,7 @@ impl Cpu {
             Interrups::DirInv,
         )?;
 
-        let value_stack = state_mem.readMemory(self.registers.sp.convert())?;
+        let value_stack = state_mem.readMemory(sp_fisico)?;
         self.registers.ac = value_stack;
         self.registers.sp = new_sp;
         if !is_save_context {
@@ -1143,7 +1771,7 @@ impl Cpu {
                 cod: Interrups::InstInv,
             });
         }
-        self.clock_interrupt = data as u32;
+        self.timer.lock().unwrap().set_period(data as u32);
 
         self.result_last_program.result_instruction = Result_Instruction::String(String::from(
             format!("Se cambio las interrupciones de reloj a {:?}", data),
@@ -1151,6 +1779,62 @@ impl Cpu {
         Ok(())
     }
 
+    //Vuelve de la rutina de interrupción: restaura el contexto que guardo save_context
+    pub fn iret(&mut self) -> Result_op {
+        if self.registers.psw.modo_op == 0 {
+            return Err(Errors {
+                msg: "Falta de privilegios, se necesita modo kernel".to_string(),
+                cod: Interrups::InstInv,
+            });
+        }
+        //El psw del marco decide si se vuelve al usuario o a la rutina que se interrumpio
+        self.restore_context()?;
+
+        if let Some(state) = self.leave_requested.take() {
+            self.switch_requested = false;
+            self.leave_current_process(state);
+        } else if self.switch_requested {
+            self.switch_requested = false;
+            self.context_switch();
+        }
+        self.result_last_program.result_instruction =
+            Result_Instruction::String(String::from("Retorno de interrupción"));
+        Ok(())
+    }
+
+    //Enmascara o desenmascara la linea de interrupción del mdr, solo las enmascarables
+    pub fn mski(&mut self, masked: bool) -> Result_op {
+        if self.registers.psw.modo_op == 0 {
+            return Err(Errors {
+                msg: "Falta de privilegios, se necesita modo kernel".to_string(),
+                cod: Interrups::InstInv,
+            });
+        }
+
+        let code = self.registers.mdr.convert();
+        let line = match Interrups::by_code(code) {
+            Some(line) if line.maskable() => line,
+            _ => {
+                return Err(Errors {
+                    msg: format!("La linea {} no se puede enmascarar", code),
+                    cod: Interrups::CodIntInv,
+                });
+            }
+        };
+        self.external_interrupt
+            .lock()
+            .unwrap()
+            .set_mask(line, masked);
+
+        self.result_last_program.result_instruction =
+            Result_Instruction::String(String::from(format!(
+                "Se {} la linea de interrupción {:?}",
+                if masked { "enmascaro" } else { "desenmascaro" },
+                line
+            )));
+        Ok(())
+    }
+
     pub fn chmod(&mut self) -> Result_op {
         if self.registers.psw.modo_op == 0 {
             return Err(Errors {
@@ -1259,7 +1943,8 @@ impl Cpu {
         Ok(())
     }
     pub fn sdmam(&mut self) -> Result_op {
-        self.dma_temp.pos_men = self.registers.mdr.convert();
+        self.dma_logical = self.registers.mdr.convert();
+        self.dma_temp.pos_men = self.translate(self.dma_logical)?;
         self.result_last_program.result_instruction =
             Result_Instruction::String(String::from(format!(
                 " Se seteo la posicion de memoria en el dma {:?}",
@@ -1267,14 +1952,100 @@ impl Cpu {
             )));
         Ok(())
     }
+    pub fn sdmacn(&mut self) -> Result_op {
+        let count = self.registers.mdr.convert();
+        if count < 1 {
+            return Err(Errors {
+                msg: "La cantidad de palabras del dma debe ser al menos 1".to_string(),
+                cod: Interrups::DirInv,
+            });
+        }
+        self.dma_temp.count = count;
+        self.result_last_program.result_instruction = Result_Instruction::String(String::from(
+            format!(" Se seteo la cantidad de palabras en el dma {:?}", count),
+        ));
+        Ok(())
+    }
+    //Carga en el ac el estado de la ultima operación del dma del proceso actual, sin proceso (el kernel)
+    //lee el registro del dma
+    pub fn ldmae(&mut self) -> Result_op {
+        let own = self.current_process.and_then(|pid| {
+            let table = self.table_proccess.lock().unwrap();
+            table.iter().find(|p| p.pid == pid).map(|p| p.dma_status)
+        });
+        let status = match own {
+            Some(status) => status,
+            None => self.external_interrupt.lock().unwrap().dma_status,
+        };
+        self.registers.ac = convert_option_result(
+            Palabra::new(&convert_to_string_format_pal(status.code())),
+            "Error al transformar el estado del dma".to_string(),
+            Interrups::InstInv,
+        )?;
+        self.result_last_program.result_instruction = Result_Instruction::String(String::from(
+            format!(" Se leyo el estado del dma {:?}", status.code()),
+        ));
+        Ok(())
+    }
     pub fn sdmaon(&mut self) -> Result_op {
+        //En modo usuario toda la transferencia tiene que caer dentro del espacio del proceso (rb..rl),
+        //con paginación rb es 0 y rl el limite logico
+        if self.registers.psw.modo_op == 0 {
+            let last = self.dma_logical + self.dma_temp.count - 1;
+            if self.dma_logical < self.registers.rb.convert() || last >= self.registers.rl.convert()
+            {
+                self.result_last_program.result_instruction =
+                    Result_Instruction::String(String::from("Transferencia dma fuera del proceso"));
+                return Err(Errors {
+                    msg: format!(
+                        "La transferencia dma {}..{} sale del proceso",
+                        self.dma_logical, last
+                    ),
+                    cod: Interrups::DirInv,
+                });
+            }
+        }
+
+        //La dirección fisica se vuelve a sacar de la logica por si el proceso se movio desde sdmam
+        self.dma_temp.pos_men = self.translate(self.dma_logical)?;
+
+        //Con paginación las palabras tienen que quedar seguidas en memoria fisica, el dma no traduce
+        if self.mmu.enabled() && self.registers.psw.modo_op == 0 {
+            for i in 1..self.dma_temp.count {
+                if self.translate(self.dma_logical + i)? != self.dma_temp.pos_men + i {
+                    return Err(Errors {
+                        msg: "La transferencia del dma cruza paginas no contiguas".to_string(),
+                        cod: Interrups::DirInv,
+                    });
+                }
+            }
+        }
+
         self.result_last_program.result_instruction =
             Result_Instruction::String(String::from(format!(" Se inicio la operación dma ")));
+        self.dma_temp.pid = self.current_process.unwrap_or(-1);
+        self.dma_temp.sent = self.disk.lock().unwrap().now();
         convert_result(
             self.sender_dma.send(self.dma_temp),
             "Error al enviar orden dma".to_string(),
             Interrups::InstInv,
         )?;
+        if let Some(pid) = self.current_process {
+            let mut table = self.table_proccess.lock().unwrap();
+            if let Some(program) = table.iter_mut().find(|p| p.pid == pid) {
+                program.pending_io += 1;
+                program.dma_status = Dma_Status {
+                    estado: State_Dma::Busy,
+                    cod_error: DMA_ERR_NONE,
+                    pid,
+                };
+            }
+        }
+
+        //En modo usuario el proceso se bloquea hasta que el dma lance el fin de E/S
+        if self.registers.psw.modo_op == 0 && self.current_process.is_some() {
+            self.block_requested = true;
+        }
         Ok(())
     }
 }
@@ -1,15 +1,187 @@
-use crate::hardware::interrupts::Interrups;
-use crate::utils::{Errors, Result_op};
+use std::{
+    fs::{self, OpenOptions},
+    io::{Seek, SeekFrom, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::hardware::{interrupts::Interrups, timer::Timer};
+use crate::utils::{Errors, Result_op, convert_result};
 pub type SectorData = [u8; 9];
+
+//Geometria del disco: cilindros, pistas por cilindro y sectores por pista
+pub const CILINDROS: i8 = 11;
+pub const PISTAS: i8 = 11;
+pub const SECTORES: i8 = 101;
+
+//Bytes de la imagen del disco, los sectores van en orden cilindro, pista, sector
+const IMAGE_SIZE: usize = CILINDROS as usize * PISTAS as usize * SECTORES as usize * 9;
+
+//Tiempos del disco en ticks simulados: mover la cabeza un cilindro, asentarla al llegar y pasar un
+//sector bajo la cabeza. El plato da una vuelta cada SECTORES ticks
+pub const TICK_MS: u64 = 2;
+pub const SEEK_TICKS: u64 = 3;
+pub const SETTLE_TICKS: u64 = 2;
+pub const SECTOR_TICKS: u64 = 1;
+
+#[derive(Debug)]
 pub struct Disk {
     disk: [[[SectorData; 101]; 11]; 11],
+    //Archivo del host que respalda el disco, None si el disco solo esta en memoria
+    pub path: Option<String>,
+    //Cilindro donde esta la cabeza, la posición de rotación sale de la hora del sistema
+    pub head_cil: i8,
+    //Temporizador del sistema, el plato gira con su hora y cada acceso la adelanta lo que tarda
+    clock: Arc<Mutex<Timer>>,
 }
 
 impl Disk {
-    pub fn new() -> Self {
+    pub fn new(clock: Arc<Mutex<Timer>>) -> Self {
         Disk {
             disk: [[[[48; 9]; 101]; 11]; 11],
+            path: None,
+            head_cil: 0,
+            clock,
+        }
+    }
+
+    //Hora del sistema en ticks simulados
+    pub fn now(&self) -> u64 {
+        self.clock.lock().unwrap().time
+    }
+
+    //Sector que esta pasando bajo la cabeza en el tick
+    pub fn rotational_position(tick: u64) -> i8 {
+        ((tick / SECTOR_TICKS) % SECTORES as u64) as i8
+    }
+
+    //Ticks que tarda acceder a count sectores seguidos desde (cil, pista, sec): busqueda del cilindro,
+    //espera a que el sector pase bajo la cabeza y transferencia. movement son los cilindros que recorre la
+    //cabeza para llegar, que con scan y cscan incluye el viaje hasta el borde. Deja la cabeza en el ultimo
+    //cilindro leido
+    pub fn access_time(&mut self, movement: i32, cil: i8, pista: i8, sec: i8, count: i32) -> u64 {
+        let distance = movement.unsigned_abs() as u64;
+        let seek = if distance > 0 {
+            distance * SEEK_TICKS + SETTLE_TICKS
+        } else {
+            0
+        };
+
+        let position = Self::rotational_position(self.now() + seek);
+        let latency =
+            (sec as i64 - position as i64).rem_euclid(SECTORES as i64) as u64 * SECTOR_TICKS;
+        let transfer = count.max(1) as u64 * SECTOR_TICKS;
+
+        let last = (pista as i32 * SECTORES as i32 + sec as i32 + count.max(1) - 1)
+            / (PISTAS as i32 * SECTORES as i32);
+        self.head_cil = (cil as i32 + last).min(CILINDROS as i32 - 1) as i8;
+
+        let ticks = seek + latency + transfer;
+        self.clock.lock().unwrap().advance(ticks);
+        ticks
+    }
+
+    //Espera real de un acceso que tarda ticks
+    pub fn wait_ticks(ticks: u64) -> Duration {
+        Duration::from_millis(ticks * TICK_MS)
+    }
+
+    //Carga el disco desde la imagen, si el archivo no existe se crea con el disco vacio
+    pub fn mount(&mut self, path: &str) -> Result_op {
+        let mut disk = Disk::new(Arc::clone(&self.clock));
+
+        if Path::new(path).exists() {
+            let bytes = convert_result(
+                fs::read(path),
+                format!("Error al leer la imagen {}", path),
+                Interrups::EndIO,
+            )?;
+            if bytes.len() != IMAGE_SIZE {
+                return Err(Errors {
+                    msg: format!("La imagen {} no tiene el tamaño del disco", path),
+                    cod: Interrups::EndIO,
+                });
+            }
+            for (i, sector) in bytes.chunks(9).enumerate() {
+                let (cil, pista, sec) = Self::sector_of_offset(i);
+                disk.disk[cil][pista][sec].copy_from_slice(sector);
+            }
+            disk.path = Some(path.to_string());
+        } else {
+            //Si la imagen no existe se crea con el disco vacio
+            disk.path = Some(path.to_string());
+            disk.save()?;
         }
+
+        //La cabeza es del dispositivo, no de la imagen
+        disk.head_cil = self.head_cil;
+        *self = disk;
+        Ok(())
+    }
+
+    //Escribe el disco completo en su imagen
+    pub fn save(&self) -> Result_op {
+        let path = match &self.path {
+            Some(path) => path,
+            None => {
+                return Err(Errors {
+                    msg: "El disco no tiene imagen montada".to_string(),
+                    cod: Interrups::EndIO,
+                });
+            }
+        };
+
+        let mut bytes = Vec::with_capacity(IMAGE_SIZE);
+        for cil in self.disk.iter() {
+            for pista in cil.iter() {
+                for sector in pista.iter() {
+                    bytes.extend_from_slice(sector);
+                }
+            }
+        }
+
+        convert_result(
+            fs::write(path, bytes),
+            format!("Error al guardar la imagen {}", path),
+            Interrups::EndIO,
+        )
+    }
+
+    fn sector_of_offset(i: usize) -> (usize, usize, usize) {
+        let sectores = SECTORES as usize;
+        let pistas = PISTAS as usize;
+        (
+            i / (pistas * sectores),
+            (i / sectores) % pistas,
+            i % sectores,
+        )
+    }
+
+    //Pasa un sector escrito a la imagen sin reescribir todo el archivo
+    fn flush_sector(&self, cil: i8, pista: i8, sec: i8) -> Result_op {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let offset =
+            ((cil as u64 * PISTAS as u64 + pista as u64) * SECTORES as u64 + sec as u64) * 9;
+        let mut file = convert_result(
+            OpenOptions::new().write(true).open(path),
+            format!("Error al abrir la imagen {}", path),
+            Interrups::EndIO,
+        )?;
+        convert_result(
+            file.seek(SeekFrom::Start(offset)),
+            "Error al posicionarse en la imagen".to_string(),
+            Interrups::EndIO,
+        )?;
+        convert_result(
+            file.write_all(&self.disk[cil as usize][pista as usize][sec as usize]),
+            "Error al escribir en la imagen".to_string(),
+            Interrups::EndIO,
+        )
     }
 
     pub fn read(&self, cil: i8, pista: i8, sec: i8) -> Result<String, Errors> {
@@ -47,6 +219,17 @@ impl Disk {
 
         self.disk[cil as usize][pista as usize][sec as usize] = bloque;
 
-        Ok(())
+        self.flush_sector(cil, pista, sec)
+    }
+}
+
+//Sector que sigue a (cil, pista, sec), al terminar la pista pasa a la siguiente y al terminar el cilindro al siguiente
+pub fn next_sector(cil: i8, pista: i8, sec: i8) -> (i8, i8, i8) {
+    if sec + 1 < SECTORES {
+        (cil, pista, sec + 1)
+    } else if pista + 1 < PISTAS {
+        (cil, pista + 1, 0)
+    } else {
+        (cil + 1, 0, 0)
     }
 }
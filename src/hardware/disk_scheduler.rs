@@ -0,0 +1,218 @@
+use std::fmt::Debug;
+
+use crate::hardware::{disk::CILINDROS, dma::Dma_Request};
+
+const LAST_CIL: i32 = CILINDROS as i32 - 1;
+
+//Politica que elige el siguiente pedido de la cola del dma según el cilindro donde esta la cabeza
+pub trait Disk_Scheduler: Send + Debug {
+    fn name(&self) -> String;
+
+    //Devuelve la posición del pedido elegido y los cilindros que se mueve la cabeza para llegar
+    fn select(&mut self, queue: &Vec<Dma_Request>, head: i8) -> Option<(usize, i32)>;
+}
+
+//Pedido mas cercano a la cabeza entre los que cumplen el filtro, empatando por orden de llegada
+fn nearest(
+    queue: &Vec<Dma_Request>,
+    from: i32,
+    filter: impl Fn(i32) -> bool,
+) -> Option<(usize, i32)> {
+    queue
+        .iter()
+        .enumerate()
+        .map(|(pos, r)| (pos, r.config.cil_acceder as i32))
+        .filter(|&(_, cil)| filter(cil))
+        .min_by_key(|&(pos, cil)| ((cil - from).abs(), pos))
+        .map(|(pos, cil)| (pos, (cil - from).abs()))
+}
+
+#[derive(Debug)]
+pub struct Fcfs_Disk;
+
+impl Disk_Scheduler for Fcfs_Disk {
+    fn name(&self) -> String {
+        "fcfs".to_string()
+    }
+
+    fn select(&mut self, queue: &Vec<Dma_Request>, head: i8) -> Option<(usize, i32)> {
+        let request = queue.first()?;
+        Some((0, (request.config.cil_acceder as i32 - head as i32).abs()))
+    }
+}
+
+//Primero el pedido mas cercano a la cabeza
+#[derive(Debug)]
+pub struct Sstf;
+
+impl Disk_Scheduler for Sstf {
+    fn name(&self) -> String {
+        "sstf".to_string()
+    }
+
+    fn select(&mut self, queue: &Vec<Dma_Request>, head: i8) -> Option<(usize, i32)> {
+        nearest(queue, head as i32, |_| true)
+    }
+}
+
+//Ascensor: sigue en su dirección hasta el ultimo cilindro del disco y despues vuelve
+#[derive(Debug)]
+pub struct Scan {
+    pub up: bool,
+}
+
+impl Disk_Scheduler for Scan {
+    fn name(&self) -> String {
+        "scan".to_string()
+    }
+
+    fn select(&mut self, queue: &Vec<Dma_Request>, head: i8) -> Option<(usize, i32)> {
+        let head = head as i32;
+        let up = self.up;
+        if let Some(found) = nearest(
+            queue,
+            head,
+            |cil| if up { cil >= head } else { cil <= head },
+        ) {
+            return Some(found);
+        }
+
+        //No hay pedidos adelante, la cabeza llega al borde y da la vuelta
+        let edge = if up { LAST_CIL } else { 0 };
+        let (pos, movement) = nearest(queue, edge, |_| true)?;
+        self.up = !up;
+        Some((pos, (edge - head).abs() + movement))
+    }
+}
+
+//Scan circular: solo atiende subiendo, al llegar al final vuelve al cilindro 0 y sigue subiendo
+#[derive(Debug)]
+pub struct C_Scan;
+
+impl Disk_Scheduler for C_Scan {
+    fn name(&self) -> String {
+        "cscan".to_string()
+    }
+
+    fn select(&mut self, queue: &Vec<Dma_Request>, head: i8) -> Option<(usize, i32)> {
+        let head = head as i32;
+        if let Some(found) = nearest(queue, head, |cil| cil >= head) {
+            return Some(found);
+        }
+
+        //El viaje de vuelta al cilindro 0 tambien se cuenta como movimiento
+        let (pos, movement) = nearest(queue, 0, |_| true)?;
+        Some((pos, (LAST_CIL - head) + LAST_CIL + movement))
+    }
+}
+
+//Como scan pero da la vuelta en el ultimo pedido en vez de ir hasta el borde
+#[derive(Debug)]
+pub struct Look {
+    pub up: bool,
+}
+
+impl Disk_Scheduler for Look {
+    fn name(&self) -> String {
+        "look".to_string()
+    }
+
+    fn select(&mut self, queue: &Vec<Dma_Request>, head: i8) -> Option<(usize, i32)> {
+        let head = head as i32;
+        let up = self.up;
+        if let Some(found) = nearest(
+            queue,
+            head,
+            |cil| if up { cil >= head } else { cil <= head },
+        ) {
+            return Some(found);
+        }
+
+        let found = nearest(queue, head, |_| true)?;
+        self.up = !up;
+        Some(found)
+    }
+}
+
+pub fn disk_scheduler_by_name(name: &str) -> Option<Box<dyn Disk_Scheduler>> {
+    match name {
+        "fcfs" => Some(Box::new(Fcfs_Disk)),
+        "sstf" => Some(Box::new(Sstf)),
+        "scan" => Some(Box::new(Scan { up: true })),
+        "cscan" => Some(Box::new(C_Scan)),
+        "look" => Some(Box::new(Look { up: true })),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::hardware::{
+        disk::{Disk, SECTORES, SEEK_TICKS, SETTLE_TICKS},
+        dma::{Dma_Config, Dma_Queue},
+        timer::Timer,
+    };
+
+    //Ticks de leer el sector 0 en el tick now moviendo la cabeza movement cilindros
+    fn expected_service(now: u64, movement: u64) -> u64 {
+        let seek = if movement > 0 {
+            movement * SEEK_TICKS + SETTLE_TICKS
+        } else {
+            0
+        };
+        let latency = (SECTORES as u64 - (now + seek) % SECTORES as u64) % SECTORES as u64;
+        seek + latency + 1
+    }
+
+    //Atiende toda la cola con la politica desde el cilindro 5, devuelve los cilindros en el orden
+    //atendido y el movimiento total. Revisa que cada acceso cobre la busqueda del movimiento reportado
+    fn serve_all(policy: &str, cils: &[i8]) -> (Vec<i8>, i32) {
+        let mut disk = Disk::new(Arc::new(Mutex::new(Timer::new())));
+        disk.head_cil = 5;
+        let mut queue = Dma_Queue::new();
+        queue.policy = disk_scheduler_by_name(policy).unwrap();
+        for &cil in cils {
+            let mut config = Dma_Config::new();
+            config.cil_acceder = cil;
+            queue.push(config);
+        }
+
+        let mut order = vec![];
+        while !queue.pending.is_empty() {
+            let (before, now) = (queue.head_movement, disk.now());
+            let (request, service) = queue.next_request(&mut disk).unwrap();
+            let movement = (queue.head_movement - before) as u64;
+            assert_eq!(service, expected_service(now, movement));
+            order.push(request.config.cil_acceder);
+        }
+        (order, queue.head_movement)
+    }
+
+    #[test]
+    fn fcfs_sigue_el_orden_de_llegada() {
+        assert_eq!(serve_all("fcfs", &[8, 2, 9, 4]), (vec![8, 2, 9, 4], 21));
+    }
+
+    #[test]
+    fn sstf_elige_el_mas_cercano() {
+        assert_eq!(serve_all("sstf", &[8, 2, 9, 4]), (vec![4, 2, 8, 9], 10));
+    }
+
+    #[test]
+    fn scan_llega_al_borde_antes_de_volver() {
+        assert_eq!(serve_all("scan", &[8, 2, 9, 4]), (vec![8, 9, 4, 2], 13));
+    }
+
+    #[test]
+    fn cscan_vuelve_al_cilindro_cero() {
+        assert_eq!(serve_all("cscan", &[8, 2, 9, 4]), (vec![8, 9, 2, 4], 19));
+    }
+
+    #[test]
+    fn look_vuelve_en_el_ultimo_pedido() {
+        assert_eq!(serve_all("look", &[8, 2, 9, 4]), (vec![8, 9, 4, 2], 11));
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    sync::{Arc, Mutex, MutexGuard},
+    sync::{Arc, Mutex, MutexGuard, mpsc::Sender},
     thread,
     time::Duration,
 };
@@ -7,7 +7,8 @@ use std::{
 use crate::{
     hardware::{
         architecture::Palabra,
-        disk::Disk,
+        disk::{Disk, TICK_MS, next_sector},
+        disk_scheduler::{Disk_Scheduler, Fcfs_Disk},
         interrupts::{External_interrupt, Interrups},
         ram::Ram,
     },
@@ -19,6 +20,51 @@ pub enum State_Dma {
     Succes,
     Error,
     Off,
+    Busy,
+}
+
+//Codigos de error del registro de estado del dma
+pub const DMA_ERR_NONE: i32 = 0;
+pub const DMA_ERR_DISCO: i32 = 1;
+pub const DMA_ERR_DATO: i32 = 2;
+pub const DMA_ERR_MEMORIA: i32 = 3;
+pub const DMA_ERR_BUS: i32 = 4;
+
+//Registro de estado del dma: como termino la ultima operación, su codigo de error y el proceso que la pidio
+#[derive(Debug, Clone, Copy)]
+pub struct Dma_Status {
+    pub estado: State_Dma,
+    pub cod_error: i32,
+    pub pid: i32,
+}
+
+impl Dma_Status {
+    pub fn new() -> Self {
+        Dma_Status {
+            estado: State_Dma::Succes,
+            cod_error: DMA_ERR_NONE,
+            pid: -1,
+        }
+    }
+
+    //Valor que lee la cpu: estado * 100 + codigo de error, el estado es 0 ok, 1 ocupado y 2 error
+    pub fn code(&self) -> i32 {
+        let estado = match self.estado {
+            State_Dma::Busy => 1,
+            State_Dma::Error => 2,
+            _ => 0,
+        };
+        estado * 100 + self.cod_error
+    }
+
+    pub fn error_code(err: &Errors) -> i32 {
+        match err.cod {
+            Interrups::EndIO => DMA_ERR_DISCO,
+            Interrups::InstInv => DMA_ERR_DATO,
+            Interrups::DirInv => DMA_ERR_MEMORIA,
+            _ => DMA_ERR_BUS,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -29,6 +75,12 @@ pub struct Dma_Config {
     pub pos_men: i32,
     pub state: State_Dma,
     pub modo: i8,
+    //Pid del proceso que pidio la operación, -1 si la pidio el kernel
+    pub pid: i32,
+    //Palabras a transferir desde pos_men y el sector del dma en adelante
+    pub count: i32,
+    //Tick del disco en que la cpu mando el pedido, desde ahi se cuenta la espera en la cola
+    pub sent: u64,
 }
 
 impl Dma_Config {
@@ -40,9 +92,162 @@ impl Dma_Config {
             pos_men: 0,
             state: State_Dma::Succes,
             modo: 0,
+            pid: -1,
+            count: 1,
+            sent: 0,
+        }
+    }
+}
+//Transferencia del kernel por la cola del dma: manda el pedido y espera su fin de E/S, que atiende
+//el mismo. Quien la llama no puede tener tomados el disco ni la memoria porque los usa el hilo del dma
+pub fn kernel_transfer(
+    sender: &Sender<Dma_Config>,
+    external_interrup: &Arc<Mutex<External_interrupt>>,
+    disk: &Arc<Mutex<Disk>>,
+    mut config: Dma_Config,
+) -> Result_op {
+    config.sent = disk.lock().unwrap().now();
+    convert_result(
+        sender.send(config),
+        "Error al enviar orden dma".to_string(),
+        Interrups::InstInv,
+    )?;
+
+    loop {
+        {
+            let mut ext = external_interrup.lock().unwrap();
+            if let Some(i) = ext.end_io_pids.iter().position(|&p| p == config.pid) {
+                ext.end_io_pids.remove(i);
+                ext.int_io = !ext.end_io_pids.is_empty();
+                return Ok(());
+            }
+            if let Some(i) = ext.io_error_pids.iter().position(|&(p, _)| p == config.pid) {
+                let (_, cod_error) = ext.io_error_pids.remove(i);
+                ext.int_io_error = !ext.io_error_pids.is_empty();
+                return Err(Errors {
+                    msg: format!("Error en la transferencia del dma, codigo {}", cod_error),
+                    cod: Interrups::EndIO,
+                });
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+//Pedido que espera en la cola del dma desde que llego
+#[derive(Debug, Clone, Copy)]
+pub struct Dma_Request {
+    pub config: Dma_Config,
+    pub arrival: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Served_Request {
+    pub pid: i32,
+    pub cil: i8,
+    pub modo: i8,
+    //Ticks que espero en la cola y que tardo el acceso al disco
+    pub wait: u64,
+    pub service: u64,
+}
+
+//Cola de pedidos del dma con la politica de planificación del disco y las estadisticas de lo atendido
+#[derive(Debug)]
+pub struct Dma_Queue {
+    pub pending: Vec<Dma_Request>,
+    pub policy: Box<dyn Disk_Scheduler>,
+    pub head_movement: i32,
+    pub served: Vec<Served_Request>,
+}
+
+impl Dma_Queue {
+    pub fn new() -> Self {
+        Dma_Queue {
+            pending: vec![],
+            policy: Box::new(Fcfs_Disk),
+            head_movement: 0,
+            served: vec![],
+        }
+    }
+
+    pub fn push(&mut self, config: Dma_Config) {
+        self.pending.push(Dma_Request {
+            config,
+            arrival: config.sent,
+        });
+    }
+
+    //Saca de la cola el pedido que elige la politica para la cabeza en head, calcula cuanto tarda en el
+    //disco y lo anota en las estadisticas. Devuelve el pedido y los ticks del acceso
+    pub fn next_request(&mut self, disk: &mut Disk) -> Option<(Dma_Request, u64)> {
+        let (pos, movement) = self.policy.select(&self.pending, disk.head_cil)?;
+        let request = self.pending.remove(pos);
+        let config = request.config;
+
+        let now = disk.now();
+        let service = disk.access_time(
+            movement,
+            config.cil_acceder,
+            config.pista_acceder,
+            config.sector_acceder,
+            config.count,
+        );
+        self.head_movement += movement;
+        self.served.push(Served_Request {
+            pid: config.pid,
+            cil: config.cil_acceder,
+            modo: config.modo,
+            wait: now.saturating_sub(request.arrival),
+            service,
+        });
+        Some((request, service))
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.head_movement = 0;
+        self.served.clear();
+    }
+
+    pub fn print_stats(&self, head_cil: i8) {
+        println!(
+            "-> Planificador de disco: {}, cabeza en el cilindro {}, pedidos pendientes: {}",
+            self.policy.name(),
+            head_cil,
+            self.pending.len()
+        );
+        println!(
+            "{:<4} {:<5} {:>4} {:>6} {:>8} {:>8}",
+            "N", "PID", "CIL", "MODO", "ESPERA", "ACCESO"
+        );
+        for (i, served) in self.served.iter().enumerate() {
+            println!(
+                "{:<4} {:<5} {:>4} {:>6} {:>8} {:>8}",
+                i + 1,
+                served.pid,
+                served.cil,
+                if served.modo == 0 { "leer" } else { "escr" },
+                served.wait,
+                served.service
+            );
         }
+
+        let average = |value: fn(&Served_Request) -> u64| {
+            if self.served.is_empty() {
+                0.0
+            } else {
+                self.served.iter().map(value).sum::<u64>() as f64 / self.served.len() as f64
+            }
+        };
+        println!(
+            "-> Movimiento total de la cabeza: {} cilindros, espera promedio: {:.2} ticks, acceso promedio: {:.2} ticks ({} ms por tick)",
+            self.head_movement,
+            average(|s| s.wait),
+            average(|s| s.service),
+            TICK_MS
+        );
     }
 }
+
 pub struct Dma {
     pub pista_acceder: i8,
     pub sector_acceder: i8,
@@ -50,6 +255,8 @@ pub struct Dma {
     pub pos_men: i32,
     pub estado: State_Dma,
     pub modo: i8,
+    pub pid: i32,
+    pub count: i32,
 }
 
 impl Dma {
@@ -61,6 +268,8 @@ impl Dma {
             pos_men: 0,
             estado: State_Dma::Succes,
             modo: 0,
+            pid: -1,
+            count: 1,
         }
     }
 
@@ -71,7 +280,15 @@ impl Dma {
         external_interrup: &Arc<Mutex<External_interrupt>>,
     ) -> Result_op {
         let modo = self.modo;
-        if modo == 0 {
+        if self.count > 1 {
+            //Varias palabras van por bloque y el fin de E/S se lanza una sola vez al terminar
+            if modo == 0 {
+                self.read_block(disk, mem, self.count)?;
+            } else if modo == 1 {
+                self.write_block(disk, mem, self.count)?;
+            }
+            self.end_io(external_interrup)?;
+        } else if modo == 0 {
             self.read_disk(disk, mem, external_interrup)?;
         } else if modo == 1 {
             self.write_disk(disk, mem, external_interrup)?;
@@ -80,13 +297,28 @@ impl Dma {
         Ok(())
     }
 
+    fn end_io(&mut self, external_interrup: &Arc<Mutex<External_interrupt>>) -> Result_op {
+        let mut state_external_interrup = match external_interrup.lock() {
+            Ok(i) => i,
+            Err(_) => {
+                self.estado = State_Dma::Error;
+                return Err(Errors {
+                    msg: "Error lanzando la interrupción".to_string(),
+                    cod: Interrups::InstInv,
+                });
+            }
+        };
+        state_external_interrup.int_io = true;
+        state_external_interrup.end_io_pids.push(self.pid);
+        Ok(())
+    }
+
     pub fn read_disk(
         &mut self,
         disk: &Disk,
         mem: &Arc<Mutex<Ram>>,
         external_interrup: &Arc<Mutex<External_interrupt>>,
     ) -> Result_op {
-        thread::sleep(Duration::from_secs(1));
         let result = disk.read(self.cil_acceder, self.pista_acceder, self.sector_acceder);
 
         let result = match result {
@@ -148,6 +380,7 @@ impl Dma {
                 }
             };
             state_external_interrup.int_io = true;
+            state_external_interrup.end_io_pids.push(self.pid);
         }
 
         Ok(())
@@ -159,7 +392,6 @@ impl Dma {
         mem: &Arc<Mutex<Ram>>,
         external_interrup: &Arc<Mutex<External_interrupt>>,
     ) -> Result_op {
-        thread::sleep(Duration::from_secs(1));
         let pal_disk: String;
 
         {
@@ -218,8 +450,70 @@ impl Dma {
                 }
             };
             state_external_interrup.int_io = true;
+            state_external_interrup.end_io_pids.push(self.pid);
+        }
+
+        Ok(())
+    }
+
+    //Lee count sectores consecutivos desde el sector del dma y los copia a memoria desde pos_men.
+    //No lanza fin de E/S, de eso se encarga quien la llama
+    pub fn read_block(&mut self, disk: &mut Disk, mem: &Arc<Mutex<Ram>>, count: i32) -> Result_op {
+        let mut state_mem = convert_result(
+            mem.lock(),
+            "Error con la sincronización del bus".to_string(),
+            Interrups::EndIO,
+        )?;
+
+        let (mut cil, mut pista, mut sec) =
+            (self.cil_acceder, self.pista_acceder, self.sector_acceder);
+        for i in 0..count {
+            let result = disk.read(cil, pista, sec);
+            let pal = result.and_then(|data| {
+                convert_option_result(
+                    Palabra::new(&data),
+                    "Error al transformar la palabra del disco".to_string(),
+                    Interrups::InstInv,
+                )
+            });
+
+            match pal.and_then(|pal| state_mem.writeMemory(self.pos_men + i, pal)) {
+                Ok(()) => (),
+                Err(err) => {
+                    self.estado = State_Dma::Error;
+                    return Err(err);
+                }
+            }
+            (cil, pista, sec) = next_sector(cil, pista, sec);
+        }
+
+        self.estado = State_Dma::Succes;
+        Ok(())
+    }
+
+    //Escribe count palabras de memoria desde pos_men en sectores consecutivos desde el sector del dma
+    pub fn write_block(&mut self, disk: &mut Disk, mem: &Arc<Mutex<Ram>>, count: i32) -> Result_op {
+        let state_mem = convert_result(
+            mem.lock(),
+            "Error con la sincronización del bus".to_string(),
+            Interrups::EndIO,
+        )?;
+
+        let (mut cil, mut pista, mut sec) =
+            (self.cil_acceder, self.pista_acceder, self.sector_acceder);
+        for i in 0..count {
+            let result = state_mem
+                .readMemory(self.pos_men + i)
+                .and_then(|pal| disk.write(pal.convert_to_string_disk(), cil, pista, sec));
+
+            if let Err(err) = result {
+                self.estado = State_Dma::Error;
+                return Err(err);
+            }
+            (cil, pista, sec) = next_sector(cil, pista, sec);
         }
 
+        self.estado = State_Dma::Succes;
         Ok(())
     }
 }
@@ -0,0 +1,419 @@
+use crate::{
+    hardware::{
+        architecture::Palabra,
+        disk::{Disk, PISTAS, SECTORES},
+        interrupts::Interrups,
+    },
+    utils::{Errors, Result_op, convert_option_result, convert_to_string_format_pal},
+};
+
+//El sistema de archivos ocupa los cilindros 1 a 4. En el cilindro 1 la pista 0 tiene el superbloque
+//(sector 0) y el mapa de bloques (sector b para el bloque b), la pista 1 el directorio. Los bloques
+//de datos son las pistas de los cilindros 2 a 4, cada bloque es una pista completa
+pub const FS_CIL_START: i8 = 1;
+pub const FS_CIL_END: i8 = 4;
+const META_PISTA: i8 = 0;
+const DIR_PISTA: i8 = 1;
+const MAGIC: &str = "SOFS0001";
+
+pub const BLOCK_SIZE: i32 = SECTORES as i32;
+pub const NUM_BLOCKS: i32 = (FS_CIL_END - FS_CIL_START) as i32 * PISTAS as i32;
+//Cada entrada del directorio usa 3 sectores: nombre, primer bloque y tamaño en palabras
+pub const DIR_ENTRIES: i32 = SECTORES as i32 / 3;
+pub const MAX_NAME: usize = 8;
+
+//En el mapa de bloques 0 es libre, FAT_END es el ultimo bloque del archivo y otro valor es el siguiente bloque
+const FAT_END: i32 = 9999999;
+
+#[derive(Debug, Clone)]
+pub struct Dir_Entry {
+    pub name: String,
+    //Bloque 0 es entrada libre, los bloques van de 1 a NUM_BLOCKS
+    pub first_block: i32,
+    pub size: i32,
+}
+
+//Archivo abierto por un proceso: entrada del directorio y posición de lectura/escritura
+#[derive(Debug, Clone, Copy)]
+pub struct Open_File {
+    pub entry: i32,
+    pub pos: i32,
+}
+
+pub struct File_System;
+
+impl File_System {
+    fn read_number(disk: &Disk, cil: i8, pista: i8, sec: i8) -> Result<i32, Errors> {
+        let data = disk.read(cil, pista, sec)?;
+        let pal = convert_option_result(
+            Palabra::new(&data),
+            "Error sector del sistema de archivos corrupto".to_string(),
+            Interrups::EndIO,
+        )?;
+        Ok(pal.convert())
+    }
+
+    fn write_number(disk: &mut Disk, value: i32, cil: i8, pista: i8, sec: i8) -> Result_op {
+        disk.write(
+            format!("{}F", convert_to_string_format_pal(value)),
+            cil,
+            pista,
+            sec,
+        )
+    }
+
+    //Un disco sin superbloque se toma como vacio: directorio y mapa en cero son un sistema sin archivos
+    pub fn ensure_format(disk: &mut Disk) -> Result_op {
+        if disk.read(FS_CIL_START, META_PISTA, 0)? != MAGIC {
+            disk.write(format!("{}F", MAGIC), FS_CIL_START, META_PISTA, 0)?;
+        }
+        Ok(())
+    }
+
+    fn block_address(block: i32) -> (i8, i8) {
+        (
+            FS_CIL_START + 1 + ((block - 1) / PISTAS as i32) as i8,
+            ((block - 1) % PISTAS as i32) as i8,
+        )
+    }
+
+    fn fat_get(disk: &Disk, block: i32) -> Result<i32, Errors> {
+        Self::read_number(disk, FS_CIL_START, META_PISTA, block as i8)
+    }
+
+    fn fat_set(disk: &mut Disk, block: i32, value: i32) -> Result_op {
+        Self::write_number(disk, value, FS_CIL_START, META_PISTA, block as i8)
+    }
+
+    fn free_block(disk: &Disk) -> Result<Option<i32>, Errors> {
+        for block in 1..=NUM_BLOCKS {
+            if Self::fat_get(disk, block)? == 0 {
+                return Ok(Some(block));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn read_entry(disk: &Disk, entry: i32) -> Result<Dir_Entry, Errors> {
+        let sec = (entry * 3) as i8;
+        Ok(Dir_Entry {
+            name: disk
+                .read(FS_CIL_START, DIR_PISTA, sec)?
+                .trim_end()
+                .to_string(),
+            first_block: Self::read_number(disk, FS_CIL_START, DIR_PISTA, sec + 1)?,
+            size: Self::read_number(disk, FS_CIL_START, DIR_PISTA, sec + 2)?,
+        })
+    }
+
+    fn write_entry(disk: &mut Disk, entry: i32, dir_entry: &Dir_Entry) -> Result_op {
+        let sec = (entry * 3) as i8;
+        disk.write(
+            format!("{:<8}F", dir_entry.name),
+            FS_CIL_START,
+            DIR_PISTA,
+            sec,
+        )?;
+        Self::write_number(
+            disk,
+            dir_entry.first_block,
+            FS_CIL_START,
+            DIR_PISTA,
+            sec + 1,
+        )?;
+        Self::write_number(disk, dir_entry.size, FS_CIL_START, DIR_PISTA, sec + 2)
+    }
+
+    //Entradas usadas del directorio con su numero
+    pub fn list(disk: &mut Disk) -> Result<Vec<(i32, Dir_Entry)>, Errors> {
+        Self::ensure_format(disk)?;
+        let mut entries = vec![];
+        for entry in 0..DIR_ENTRIES {
+            let dir_entry = Self::read_entry(disk, entry)?;
+            if dir_entry.first_block != 0 {
+                entries.push((entry, dir_entry));
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn find(disk: &mut Disk, name: &str) -> Result<Option<i32>, Errors> {
+        Ok(Self::list(disk)?
+            .into_iter()
+            .find(|(_, e)| e.name == name)
+            .map(|(entry, _)| entry))
+    }
+
+    //Crea un archivo vacio con un bloque, devuelve su entrada
+    pub fn create(disk: &mut Disk, name: &str) -> Result<i32, Errors> {
+        if name.is_empty() || name.len() > MAX_NAME || !name.is_ascii() {
+            return Err(Errors {
+                msg: format!("Nombre de archivo invalido, hasta {} letras", MAX_NAME),
+                cod: Interrups::EndIO,
+            });
+        }
+        if Self::find(disk, name)?.is_some() {
+            return Err(Errors {
+                msg: format!("El archivo {} ya existe", name),
+                cod: Interrups::EndIO,
+            });
+        }
+
+        let mut entry = None;
+        for e in 0..DIR_ENTRIES {
+            if Self::read_entry(disk, e)?.first_block == 0 {
+                entry = Some(e);
+                break;
+            }
+        }
+        let entry = convert_option_result(
+            entry,
+            "El directorio esta lleno".to_string(),
+            Interrups::EndIO,
+        )?;
+        let block = convert_option_result(
+            Self::free_block(disk)?,
+            "No hay bloques libres en el disco".to_string(),
+            Interrups::EndIO,
+        )?;
+
+        Self::fat_set(disk, block, FAT_END)?;
+        Self::write_entry(
+            disk,
+            entry,
+            &Dir_Entry {
+                name: name.to_string(),
+                first_block: block,
+                size: 0,
+            },
+        )?;
+        Ok(entry)
+    }
+
+    pub fn delete(disk: &mut Disk, name: &str) -> Result_op {
+        let entry = convert_option_result(
+            Self::find(disk, name)?,
+            format!("No existe el archivo {}", name),
+            Interrups::EndIO,
+        )?;
+        let dir_entry = Self::read_entry(disk, entry)?;
+
+        let mut block = dir_entry.first_block;
+        while block != FAT_END && block != 0 {
+            let next = Self::fat_get(disk, block)?;
+            Self::fat_set(disk, block, 0)?;
+            block = next;
+        }
+
+        Self::write_entry(
+            disk,
+            entry,
+            &Dir_Entry {
+                name: String::new(),
+                first_block: 0,
+                size: 0,
+            },
+        )
+    }
+
+    //Bloque del archivo donde esta la palabra pos, si extend se agregan bloques al final de la cadena
+    fn block_of(disk: &mut Disk, first_block: i32, pos: i32, extend: bool) -> Result<i32, Errors> {
+        let mut block = first_block;
+        for _ in 0..pos / BLOCK_SIZE {
+            let mut next = Self::fat_get(disk, block)?;
+            if next == FAT_END {
+                if !extend {
+                    return Err(Errors {
+                        msg: "Posición fuera del archivo".to_string(),
+                        cod: Interrups::EndIO,
+                    });
+                }
+                next = convert_option_result(
+                    Self::free_block(disk)?,
+                    "No hay bloques libres en el disco".to_string(),
+                    Interrups::EndIO,
+                )?;
+                Self::fat_set(disk, block, next)?;
+                Self::fat_set(disk, next, FAT_END)?;
+            }
+            block = next;
+        }
+        Ok(block)
+    }
+
+    //Lee la palabra pos del archivo, None si esta al final
+    pub fn read_word(disk: &mut Disk, entry: i32, pos: i32) -> Result<Option<Palabra>, Errors> {
+        let dir_entry = Self::read_entry(disk, entry)?;
+        if pos < 0 || pos >= dir_entry.size {
+            return Ok(None);
+        }
+
+        let block = Self::block_of(disk, dir_entry.first_block, pos, false)?;
+        let (cil, pista) = Self::block_address(block);
+        let data = disk.read(cil, pista, (pos % BLOCK_SIZE) as i8)?;
+        Ok(Some(convert_option_result(
+            Palabra::new(&data),
+            "Error palabra del archivo corrupta".to_string(),
+            Interrups::EndIO,
+        )?))
+    }
+
+    //Escribe la palabra pos del archivo, si es despues del final el archivo crece hasta ahi
+    pub fn write_word(disk: &mut Disk, entry: i32, pos: i32, pal: Palabra) -> Result_op {
+        let mut dir_entry = Self::read_entry(disk, entry)?;
+        if pos < 0 || pos > dir_entry.size {
+            return Err(Errors {
+                msg: "Posición fuera del archivo".to_string(),
+                cod: Interrups::EndIO,
+            });
+        }
+
+        let block = Self::block_of(disk, dir_entry.first_block, pos, true)?;
+        let (cil, pista) = Self::block_address(block);
+        disk.write(
+            pal.convert_to_string_disk(),
+            cil,
+            pista,
+            (pos % BLOCK_SIZE) as i8,
+        )?;
+
+        if pos == dir_entry.size {
+            dir_entry.size += 1;
+            Self::write_entry(disk, entry, &dir_entry)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_file(disk: &mut Disk, name: &str) -> Result<Vec<Palabra>, Errors> {
+        let entry = convert_option_result(
+            Self::find(disk, name)?,
+            format!("No existe el archivo {}", name),
+            Interrups::EndIO,
+        )?;
+
+        let mut words = vec![];
+        while let Some(pal) = Self::read_word(disk, entry, words.len() as i32)? {
+            words.push(pal);
+        }
+        Ok(words)
+    }
+
+    //Crea el archivo con las palabras, si ya existe se reemplaza
+    pub fn write_file(disk: &mut Disk, name: &str, words: &[Palabra]) -> Result_op {
+        if Self::find(disk, name)?.is_some() {
+            Self::delete(disk, name)?;
+        }
+
+        let entry = Self::create(disk, name)?;
+        for (pos, pal) in words.iter().enumerate() {
+            Self::write_word(disk, entry, pos as i32, *pal)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::hardware::timer::Timer;
+
+    fn disk() -> Disk {
+        Disk::new(Arc::new(Mutex::new(Timer::new())))
+    }
+
+    fn pal(value: i32) -> Palabra {
+        Palabra::new(&convert_to_string_format_pal(value)).unwrap()
+    }
+
+    //Bloques de la cadena del archivo en el mapa de bloques
+    fn chain(disk: &Disk, first_block: i32) -> Vec<i32> {
+        let mut blocks = vec![];
+        let mut block = first_block;
+        while block != FAT_END {
+            blocks.push(block);
+            block = File_System::fat_get(disk, block).unwrap();
+        }
+        blocks
+    }
+
+    #[test]
+    fn crear_y_escribir_a_traves_de_varios_bloques() {
+        let mut disk = disk();
+        let a = File_System::create(&mut disk, "a").unwrap();
+        let b = File_System::create(&mut disk, "b").unwrap();
+        assert!(File_System::create(&mut disk, "a").is_err());
+
+        let words = 2 * BLOCK_SIZE + 5;
+        for pos in 0..words {
+            File_System::write_word(&mut disk, a, pos, pal(pos * 3)).unwrap();
+        }
+        File_System::write_word(&mut disk, b, 0, pal(77)).unwrap();
+
+        let entry = File_System::read_entry(&disk, a).unwrap();
+        assert_eq!(entry.size, words);
+        //El bloque 2 es de b, la cadena de a lo salta
+        assert_eq!(chain(&disk, entry.first_block), vec![1, 3, 4]);
+
+        for pos in [0, BLOCK_SIZE - 1, BLOCK_SIZE, words - 1] {
+            let read = File_System::read_word(&mut disk, a, pos).unwrap().unwrap();
+            assert_eq!(read.convert(), pos * 3);
+        }
+        assert!(
+            File_System::read_word(&mut disk, a, words)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            File_System::read_word(&mut disk, b, 0)
+                .unwrap()
+                .unwrap()
+                .convert(),
+            77
+        );
+    }
+
+    #[test]
+    fn escribir_despues_del_final_falla() {
+        let mut disk = disk();
+        let a = File_System::create(&mut disk, "a").unwrap();
+        assert!(File_System::write_word(&mut disk, a, 1, pal(1)).is_err());
+    }
+
+    #[test]
+    fn borrar_libera_la_cadena() {
+        let mut disk = disk();
+        let words: Vec<Palabra> = (0..BLOCK_SIZE + 1).map(pal).collect();
+        File_System::write_file(&mut disk, "a", &words).unwrap();
+        let entry = File_System::find(&mut disk, "a").unwrap().unwrap();
+        let first_block = File_System::read_entry(&disk, entry).unwrap().first_block;
+        assert_eq!(chain(&disk, first_block), vec![1, 2]);
+
+        File_System::delete(&mut disk, "a").unwrap();
+        assert!(File_System::find(&mut disk, "a").unwrap().is_none());
+        assert_eq!(File_System::fat_get(&disk, 1).unwrap(), 0);
+        assert_eq!(File_System::fat_get(&disk, 2).unwrap(), 0);
+        assert!(File_System::delete(&mut disk, "a").is_err());
+
+        //Los bloques liberados se vuelven a usar
+        let b = File_System::create(&mut disk, "b").unwrap();
+        assert_eq!(File_System::read_entry(&disk, b).unwrap().first_block, 1);
+    }
+
+    #[test]
+    fn leer_y_reemplazar_un_archivo_completo() {
+        let mut disk = disk();
+        let words: Vec<Palabra> = (0..5).map(pal).collect();
+        File_System::write_file(&mut disk, "a", &words).unwrap();
+        File_System::write_file(&mut disk, "a", &words[..2]).unwrap();
+
+        let read: Vec<i32> = File_System::read_file(&mut disk, "a")
+            .unwrap()
+            .iter()
+            .map(|p| p.convert())
+            .collect();
+        assert_eq!(read, vec![0, 1]);
+        assert_eq!(File_System::list(&mut disk).unwrap().len(), 1);
+    }
+}
@@ -1,11 +1,27 @@
 use std::{
+    collections::VecDeque,
     marker,
     sync::{Arc, Mutex},
 };
 
 use crate::{
-    hardware::{architecture::Palabra, ram::Ram, registers::Registros},
-    utils::ContinueOrBreak,
+    Programs, State_Process,
+    hardware::{
+        allocator::Memory_Manager,
+        architecture::Palabra,
+        console::Console,
+        disk::Disk,
+        dma::{DMA_ERR_NONE, Dma_Status, State_Dma},
+        filesystem::{File_System, Open_File},
+        mmu::{Mmu, PAGE_SIZE},
+        ram::Ram,
+        registers::Registros,
+        timer::Timer,
+    },
+    utils::{
+        ContinueOrBreak, Errors, Result_op, convert_option_result, convert_to_string_format_pal,
+        evict_page, load_page, set_ready,
+    },
 };
 #[derive(Debug)]
 pub struct External_interrupt {
@@ -18,6 +34,23 @@ pub struct External_interrupt {
     pub int_call_sys: bool,
     pub int_cod_inte_inv: bool,
     pub int_cod_callsys_inv: bool,
+    pub int_page_fault: bool,
+    pub int_stack_overflow: bool,
+    pub int_io_error: bool,
+    //Pids de los procesos cuya operación de E/S termino
+    pub end_io_pids: Vec<i32>,
+    //Pids de los procesos cuya operación de E/S fallo, con el codigo de error de su operación
+    pub io_error_pids: Vec<(i32, i32)>,
+    //Registro de estado del dma, lo escribe el dma. Cada proceso lee con ldmae el de su ultima operación,
+    //que se guarda en su pcb al atender el fin o el error de E/S
+    pub dma_status: Dma_Status,
+    //Proceso y pagina del ultimo fallo de pagina
+    pub fault_pid: i32,
+    pub fault_page: i32,
+    //Pagina de la instrucción que fallo, no se reemplaza mientras se atiende el fallo de su operando
+    pub fault_pc_page: i32,
+    //Lineas enmascaradas, un bit por codigo de interrupción
+    pub mask: u16,
 }
 
 impl External_interrupt {
@@ -32,12 +65,127 @@ impl External_interrupt {
             int_call_sys: false,
             int_cod_inte_inv: false,
             int_cod_callsys_inv: false,
+            int_page_fault: false,
+            int_stack_overflow: false,
+            int_io_error: false,
+            end_io_pids: vec![],
+            io_error_pids: vec![],
+            dma_status: Dma_Status::new(),
+            fault_pid: -1,
+            fault_page: -1,
+            fault_pc_page: -1,
+            mask: 0,
         }
     }
+
+    pub fn is_raised(&self, line: Interrups) -> bool {
+        match line {
+            Interrups::StackOverflow => self.int_stack_overflow,
+            Interrups::PageFault => self.int_page_fault,
+            Interrups::Overflow => self.int_overflow,
+            Interrups::Underflow => self.int_underflow,
+            Interrups::DirInv => self.int_dir_inv,
+            Interrups::InstInv => self.int_inst_inv,
+            Interrups::EndIO => self.int_io,
+            Interrups::Clock => self.int_clock,
+            Interrups::CallSys => self.int_call_sys,
+            Interrups::CodIntInv => self.int_cod_inte_inv,
+            Interrups::CodCallSysInv => self.int_cod_callsys_inv,
+            Interrups::IoError => self.int_io_error,
+        }
+    }
+
+    pub fn is_masked(&self, line: Interrups) -> bool {
+        self.mask & (1 << line as u16) != 0
+    }
+
+    pub fn set_mask(&mut self, line: Interrups, masked: bool) {
+        if masked {
+            self.mask |= 1 << line as u16;
+        } else {
+            self.mask &= !(1 << line as u16);
+        }
+    }
+
+    //Interrupción pendiente de mayor prioridad que se puede atender. Las fallas siempre se entregan,
+    //las enmascarables quedan pendientes si inte esta apagado o su linea esta enmascarada
+    pub fn next_pending(&self, inte: bool) -> Option<Interrups> {
+        PRIORITY.iter().copied().find(|&line| {
+            self.is_raised(line) && (!line.maskable() || (inte && !self.is_masked(line)))
+        })
+    }
+
+    //Limpia las interrupciones que genero el proceso actual, para que no las reciba el siguiente
+    pub fn clear_process_interrupts(&mut self) {
+        self.int_overflow = false;
+        self.int_underflow = false;
+        self.int_dir_inv = false;
+        self.int_inst_inv = false;
+        self.int_call_sys = false;
+        self.int_cod_inte_inv = false;
+        self.int_cod_callsys_inv = false;
+        self.int_page_fault = false;
+        self.int_stack_overflow = false;
+    }
 }
 
-#[derive(Debug)]
+//El vector tiene una entrada por interrupción con la dirección de su rutina. Las rutinas por defecto
+//van desde HANDLERS_START, dos palabras cada una: la instrucción de servicio del kernel y iret
+pub const VECTOR_SIZE: i32 = 12;
+pub const HANDLERS_START: i32 = 12;
+pub const HANDLER_SIZE: i32 = 2;
+//Pila del kernel donde se guardan los marcos de las interrupciones, crece hacia abajo desde KSTACK_TOP
+pub const KSTACK_START: i32 = 70;
+pub const KSTACK_TOP: i32 = 100;
+const IRET: &str = "36000000";
+
+//Instrucción de servicio que atiende la interrupción de la entrada del vector
+fn service_opcode(vector: i32) -> i32 {
+    match vector {
+        10 => 89,
+        11 => 88,
+        v => 90 + v,
+    }
+}
+
+//Escribe el vector con las rutinas por defecto
+pub fn install_vector(ram: &mut Ram) -> Result_op {
+    for vector in 0..VECTOR_SIZE {
+        let handler = HANDLERS_START + vector * HANDLER_SIZE;
+        ram.writeMemory(
+            vector,
+            Palabra::new(&convert_to_string_format_pal(handler)).unwrap(),
+        )?;
+        ram.writeMemory(
+            handler,
+            Palabra::new(&format!("{}000000", service_opcode(vector))).unwrap(),
+        )?;
+        ram.writeMemory(handler + 1, Palabra::new(IRET).unwrap())?;
+    }
+    Ok(())
+}
+
+//Prioridad de las lineas de mayor a menor: primero las fallas y trampas del proceso, despues las externas
+pub const PRIORITY: [Interrups; 12] = [
+    Interrups::Overflow,
+    Interrups::Underflow,
+    Interrups::DirInv,
+    Interrups::InstInv,
+    Interrups::StackOverflow,
+    Interrups::PageFault,
+    Interrups::CallSys,
+    Interrups::CodIntInv,
+    Interrups::CodCallSysInv,
+    Interrups::IoError,
+    Interrups::EndIO,
+    Interrups::Clock,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Interrups {
+    IoError = 11,
+    StackOverflow = 10,
+    PageFault = 9,
     Overflow = 8,
     Underflow = 7,
     DirInv = 6,
@@ -49,6 +197,20 @@ pub enum Interrups {
     CodCallSysInv = 0,
 }
 
+impl Interrups {
+    pub fn by_code(code: i32) -> Option<Interrups> {
+        PRIORITY.iter().copied().find(|&line| line as i32 == code)
+    }
+
+    //Reloj y E/S vienen de afuera del proceso y pueden esperar
+    pub fn maskable(&self) -> bool {
+        matches!(
+            self,
+            Interrups::Clock | Interrups::EndIO | Interrups::IoError
+        )
+    }
+}
+
 pub fn overflow(regs: &mut Registros) -> ContinueOrBreak {
     ContinueOrBreak::Break
 }
@@ -62,13 +224,71 @@ pub fn dir_inv() -> ContinueOrBreak {
     ContinueOrBreak::Break
 }
 
+pub fn stack_overflow() -> ContinueOrBreak {
+    println!("Desbordamiento de pila");
+    ContinueOrBreak::Break
+}
+
 pub fn inst_inv() -> ContinueOrBreak {
     println!("Instrucción Invalido");
     ContinueOrBreak::Break
 }
 
-pub fn end_io() -> ContinueOrBreak {
-    println!("Termino I/O");
+//Despierta a los dueños de las operaciones de E/S que terminaron
+pub fn end_io(
+    external_int: Arc<Mutex<External_interrupt>>,
+    table_process: Arc<Mutex<Vec<Programs>>>,
+) -> ContinueOrBreak {
+    let pids: Vec<i32> = {
+        let mut lock_int = external_int.lock().unwrap();
+        lock_int.end_io_pids.drain(..).collect()
+    };
+
+    let mut table = table_process.lock().unwrap();
+    for pid in pids {
+        println!("Termino I/O del proceso {pid}");
+        if let Some(pos) = table.iter().position(|p| p.pid == pid) {
+            table[pos].dma_status = Dma_Status {
+                estado: State_Dma::Succes,
+                cod_error: DMA_ERR_NONE,
+                pid,
+            };
+            table[pos].pending_io = table[pos].pending_io.saturating_sub(1);
+            if table[pos].state == State_Process::Blocked {
+                set_ready(&mut table, pos);
+            }
+        }
+    }
+    wake_memory_waiters(&mut table);
+    ContinueOrBreak::Continue
+}
+
+//Despierta a los dueños de las operaciones que fallaron, leen el error con el registro de estado del dma
+pub fn io_error(
+    external_int: Arc<Mutex<External_interrupt>>,
+    table_process: Arc<Mutex<Vec<Programs>>>,
+) -> ContinueOrBreak {
+    let pids: Vec<(i32, i32)> = {
+        let mut lock_int = external_int.lock().unwrap();
+        lock_int.io_error_pids.drain(..).collect()
+    };
+
+    let mut table = table_process.lock().unwrap();
+    for (pid, cod_error) in pids {
+        println!("Error de I/O del proceso {pid}, codigo {cod_error}");
+        if let Some(pos) = table.iter().position(|p| p.pid == pid) {
+            table[pos].dma_status = Dma_Status {
+                estado: State_Dma::Error,
+                cod_error,
+                pid,
+            };
+            table[pos].pending_io = table[pos].pending_io.saturating_sub(1);
+            if table[pos].state == State_Process::Blocked {
+                set_ready(&mut table, pos);
+            }
+        }
+    }
+    wake_memory_waiters(&mut table);
     ContinueOrBreak::Continue
 }
 
@@ -77,59 +297,628 @@ pub fn clock() -> ContinueOrBreak {
     ContinueOrBreak::Continue
 }
 
+//Despierta a los que esperan lugar en memoria para volver de swap, se llama cuando termina un
+//proceso o una E/S y puede haber memoria para liberar
+pub fn wake_memory_waiters(table: &mut Vec<Programs>) {
+    for pos in 0..table.len() {
+        if table[pos].state == State_Process::Blocked
+            && table[pos].swap_sector >= 0
+            && table[pos].pending_io == 0
+        {
+            set_ready(table, pos);
+        }
+    }
+}
+
+//Despierta a los procesos dormidos cuyo tiempo ya paso, se revisa en cada tick aunque el
+//temporizador no interrumpa
+pub fn wake_sleepers(table: &mut Vec<Programs>, now: u64) {
+    for pos in 0..table.len() {
+        if table[pos].state == State_Process::Blocked
+            && table[pos].sleep_until.is_some_and(|t| t <= now)
+        {
+            table[pos].sleep_until = None;
+            set_ready(table, pos);
+        }
+    }
+}
+
+//Semaforo contador del kernel, los procesos que esperan quedan bloqueados en orden de llegada
+#[derive(Debug)]
+pub struct Semaphore {
+    pub id: i32,
+    pub value: i32,
+    pub waiting: VecDeque<i32>,
+}
+
+//Valores de los semaforos y los procesos que esperan en cada uno
+pub fn print_semaphores(semaphores: &Vec<Semaphore>) {
+    if semaphores.is_empty() {
+        println!("-> No hay semaforos");
+        return;
+    }
+
+    println!("{:<4} {:>6}  ESPERANDO", "ID", "VALOR");
+    for sem in semaphores {
+        println!("{:<4} {:>6}  {:?}", sem.id, sem.value, sem.waiting);
+    }
+}
+
+//Lo que pasa con el proceso despues de la llamada al sistema
+pub enum Syscall_Action {
+    //Sigue con el resultado en el ac
+    Value(i32),
+    Exit,
+    //Deja el procesador al volver con iret, listo o bloqueado
+    Yield,
+    Block,
+    //Lee un numero de la consola para el proceso (nombre, pid) despues de soltar la tabla
+    ReadInput(String, i32),
+}
+
+//Lo que ven las rutinas de la tabla: registros con el marco, el proceso que hizo la llamada y los dispositivos
+pub struct Syscall_Context<'a> {
+    regs: &'a Registros,
+    ram: &'a Arc<Mutex<Ram>>,
+    table: &'a mut Vec<Programs>,
+    pos: usize,
+    disk: &'a Arc<Mutex<Disk>>,
+    timer: &'a Arc<Mutex<Timer>>,
+    semaphores: &'a Arc<Mutex<Vec<Semaphore>>>,
+}
+
+//Entrada de la tabla de llamadas al sistema, el código es el que va en el ac antes de svc
+pub struct Syscall {
+    pub cod: i32,
+    pub name: &'static str,
+    //Argumentos que se sacan de la pila del usuario, el argumento 0 es el ultimo que se apilo
+    pub args: usize,
+    routine: fn(&mut Syscall_Context, &[i32]) -> Result<Syscall_Action, Errors>,
+}
+
+pub const SYSCALLS: [Syscall; 18] = [
+    Syscall {
+        cod: 1,
+        name: "exit",
+        args: 0,
+        routine: sys_exit,
+    },
+    Syscall {
+        cod: 2,
+        name: "time",
+        args: 0,
+        routine: sys_time,
+    },
+    Syscall {
+        cod: 3,
+        name: "print",
+        args: 1,
+        routine: sys_print,
+    },
+    Syscall {
+        cod: 4,
+        name: "printc",
+        args: 1,
+        routine: sys_print_char,
+    },
+    Syscall {
+        cod: 5,
+        name: "read",
+        args: 0,
+        routine: sys_read,
+    },
+    Syscall {
+        cod: 6,
+        name: "getpid",
+        args: 0,
+        routine: sys_getpid,
+    },
+    Syscall {
+        cod: 7,
+        name: "yield",
+        args: 0,
+        routine: sys_yield,
+    },
+    Syscall {
+        cod: 8,
+        name: "sleep",
+        args: 1,
+        routine: sys_sleep,
+    },
+    Syscall {
+        cod: 9,
+        name: "alloc",
+        args: 1,
+        routine: sys_alloc,
+    },
+    Syscall {
+        cod: 10,
+        name: "open",
+        args: 1,
+        routine: sys_open,
+    },
+    Syscall {
+        cod: 11,
+        name: "fread",
+        args: 1,
+        routine: sys_file_read,
+    },
+    Syscall {
+        cod: 12,
+        name: "fwrite",
+        args: 2,
+        routine: sys_file_write,
+    },
+    Syscall {
+        cod: 13,
+        name: "close",
+        args: 1,
+        routine: sys_close,
+    },
+    Syscall {
+        cod: 14,
+        name: "delete",
+        args: 1,
+        routine: sys_delete,
+    },
+    Syscall {
+        cod: 15,
+        name: "exitcode",
+        args: 1,
+        routine: sys_exit_code,
+    },
+    Syscall {
+        cod: 16,
+        name: "semcreate",
+        args: 1,
+        routine: sys_sem_create,
+    },
+    Syscall {
+        cod: 17,
+        name: "semwait",
+        args: 1,
+        routine: sys_sem_wait,
+    },
+    Syscall {
+        cod: 18,
+        name: "semsignal",
+        args: 1,
+        routine: sys_sem_signal,
+    },
+];
+
 pub fn call_sys(
     regs: &mut Registros,
     ram: Arc<Mutex<Ram>>,
     external_int: Arc<Mutex<External_interrupt>>,
+    table_process: Arc<Mutex<Vec<Programs>>>,
+    disk: Arc<Mutex<Disk>>,
+    timer: Arc<Mutex<Timer>>,
+    semaphores: Arc<Mutex<Vec<Semaphore>>>,
 ) -> ContinueOrBreak {
     let codCall = regs.ac.convert();
 
-    match codCall {
-        1 => {
-            println!("Llamada al sistema {}", codCall,);
-
-            return ContinueOrBreak::Break;
-        }
-        _ => {
-            //Código para llamada que reciba parametro
-
-            // let parametro;
-            // {
-            //     let state_ram = ram.lock().unwrap();
-
-            //     parametro = state_ram.readMemory(regs.sp.convert());
-            //     let pal = Palabra::new(&"00000001".to_string()).unwrap();
-            //     let new_sp = (regs.sp + pal);
-            //     match new_sp {
-            //         Ok(p) => {
-            //             regs.set_sp(p);
-            //         }
-            //         Err(err) => {
-            //             {
-            //                 external_int.lock().unwrap().int_dir_inv = true;
-            //             }
-            //             return ContinueOrBreak::Break;
-            //         }
-            //     }
-            // }
-
-            // let parametro_pal = match parametro {
-            //     Ok(pal) => pal,
-            //     Err(_) => {
-            //         {
-            //             external_int.lock().unwrap().int_dir_inv = true;
-            //         }
-            //         return ContinueOrBreak::Continue;
-            //     }
-            // };
+    let syscall = match SYSCALLS.iter().find(|s| s.cod == codCall) {
+        Some(syscall) => syscall,
+        None => {
             println!("Llamada al sistema no encontrada {codCall}");
+            external_int.lock().unwrap().int_cod_callsys_inv = true;
+            return ContinueOrBreak::Continue;
+        }
+    };
 
-            {
+    let action = {
+        let mut table = table_process.lock().unwrap();
+        let pos = match table.iter().position(|p| p.state == State_Process::Running) {
+            Some(pos) => pos,
+            None => return ContinueOrBreak::Break,
+        };
+
+        //Si en la pila no estan todos los argumentos la llamada es invalida
+        let args = match pop_syscall_args(regs, &ram, &table[pos], syscall.args) {
+            Ok(args) => args,
+            Err(e) => {
+                println!("Error en la llamada al sistema {}: {}", syscall.name, e.msg);
+                drop(table);
                 external_int.lock().unwrap().int_cod_callsys_inv = true;
+                return ContinueOrBreak::Continue;
+            }
+        };
+
+        let mut context = Syscall_Context {
+            regs,
+            ram: &ram,
+            table: &mut table,
+            pos,
+            disk: &disk,
+            timer: &timer,
+            semaphores: &semaphores,
+        };
+        match (syscall.routine)(&mut context, &args) {
+            Ok(action) => action,
+            Err(e) => {
+                println!("Error en la llamada al sistema {}: {}", syscall.name, e.msg);
+                Syscall_Action::Value(-1)
             }
         }
+    };
+
+    let (value, response) = match action {
+        Syscall_Action::Exit => return ContinueOrBreak::Exit,
+        Syscall_Action::Value(value) => (value, ContinueOrBreak::Continue),
+        Syscall_Action::Yield => (0, ContinueOrBreak::Yield),
+        Syscall_Action::Block => (0, ContinueOrBreak::Block),
+        //La tabla se suelta mientras se espera la entrada
+        Syscall_Action::ReadInput(name, pid) => match Console::read_number(&name, pid) {
+            Ok(value) => (value, ContinueOrBreak::Continue),
+            Err(e) => {
+                println!("Error en la llamada al sistema {}: {}", syscall.name, e.msg);
+                (-1, ContinueOrBreak::Continue)
+            }
+        },
+    };
+    if set_syscall_result(regs, &ram, value).is_err() {
+        return ContinueOrBreak::Break;
+    }
+    response
+}
+
+//Marco que deja save_context en la pila del kernel, desde el tope: psw (con el pc), sp, rd, rx, rl,
+//rb y ac. Las rutinas lo encuentran en su sp
+pub const FRAME_SIZE: i32 = 7;
+pub const FRAME_PSW: i32 = 0;
+pub const FRAME_SP: i32 = 1;
+pub const FRAME_RD: i32 = 2;
+pub const FRAME_RX: i32 = 3;
+pub const FRAME_RL: i32 = 4;
+pub const FRAME_RB: i32 = 5;
+pub const FRAME_AC: i32 = 6;
+
+//Registro del proceso interrumpido guardado en el marco
+fn frame_word(ram: &Ram, regs: &Registros, offset: i32) -> Result<Palabra, Errors> {
+    ram.readMemory(regs.sp.convert() + offset)
+}
+
+//Dirección fisica de una dirección del proceso, los procesos paginados pasan por su tabla de paginas
+fn user_address(ram: &Ram, program: &Programs, dir: i32) -> Result<i32, Errors> {
+    if program.ptbr < 0 {
+        return Ok(dir);
+    }
+
+    let mut mmu = Mmu::new();
+    mmu.ptbr = program.ptbr;
+    mmu.ptlr = program.pages;
+    mmu.translate(ram, dir)
+}
+
+//Saca los argumentos de la pila del usuario subiendo el sp guardado en el marco
+fn pop_syscall_args(
+    regs: &Registros,
+    ram: &Arc<Mutex<Ram>>,
+    program: &Programs,
+    count: usize,
+) -> Result<Vec<i32>, Errors> {
+    let mut ram = ram.lock().unwrap();
+    let sp = frame_word(&ram, regs, FRAME_SP)?.convert();
+    let end = sp + count as i32;
+    if end > frame_word(&ram, regs, FRAME_RL)?.convert() {
+        return Err(Errors {
+            msg: format!("Faltan argumentos en la pila, se necesitan {}", count),
+            cod: Interrups::CodCallSysInv,
+        });
+    }
+
+    let mut args = vec![];
+    for dir in sp..end {
+        args.push(ram.readMemory(user_address(&ram, program, dir)?)?.convert());
+    }
+    ram.writeMemory(
+        regs.sp.convert() + FRAME_SP,
+        Palabra::new(&convert_to_string_format_pal(end)).unwrap(),
+    )?;
+    Ok(args)
+}
+
+//El resultado se escribe en el ac del marco, asi al restaurar el contexto queda en el ac
+pub fn set_syscall_result(regs: &Registros, ram: &Arc<Mutex<Ram>>, value: i32) -> Result_op {
+    let pal = convert_option_result(
+        Palabra::new(&convert_to_string_format_pal(value)),
+        "Error resultado de la llamada al sistema invalido".to_string(),
+        Interrups::Overflow,
+    )?;
+    ram.lock()
+        .unwrap()
+        .writeMemory(regs.sp.convert() + FRAME_AC, pal)
+}
+
+fn sys_exit(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    println!("Llamada al sistema 1");
+    context.table[context.pos].exit_status = 0;
+    Ok(Syscall_Action::Exit)
+}
+
+//Termina con el estado del argumento, lo ve la terminal al terminar run
+fn sys_exit_code(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    println!("Llamada al sistema 15");
+    context.table[context.pos].exit_status = args[0];
+    Ok(Syscall_Action::Exit)
+}
+
+//Hora del dia: ticks del temporizador desde el arranque
+fn sys_time(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let time = context.timer.lock().unwrap().time;
+    Ok(Syscall_Action::Value(time as i32))
+}
+
+fn sys_print(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    Console::write_number(&mut context.table[context.pos], args[0]);
+    Ok(Syscall_Action::Value(0))
+}
+
+fn sys_print_char(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    Console::write_char(&mut context.table[context.pos], args[0])?;
+    Ok(Syscall_Action::Value(0))
+}
+
+fn sys_read(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let program = &context.table[context.pos];
+    Ok(Syscall_Action::ReadInput(program.name.clone(), program.pid))
+}
+
+fn sys_getpid(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    Ok(Syscall_Action::Value(context.table[context.pos].pid))
+}
+
+fn sys_yield(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    Ok(Syscall_Action::Yield)
+}
+
+//Duerme la cantidad de ticks del argumento, lo despierta el tick del temporizador en que se cumplen
+fn sys_sleep(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    if args[0] <= 0 {
+        return Ok(Syscall_Action::Value(0));
+    }
+
+    let time = context.timer.lock().unwrap().time;
+    context.table[context.pos].sleep_until = Some(time + args[0] as u64);
+    Ok(Syscall_Action::Block)
+}
+
+//Toma palabras entre los datos y la pila subiendo el limite de la pila (rx), devuelve la dirección
+//de la primera palabra relativa a rb como las que usa el programa
+fn sys_alloc(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let words = args[0];
+    if words < 0 {
+        return Err(Errors {
+            msg: format!("No se pueden pedir {} palabras", words),
+            cod: Interrups::DirInv,
+        });
+    }
+
+    let mut ram = context.ram.lock().unwrap();
+    let rb = frame_word(&ram, context.regs, FRAME_RB)?.convert();
+    let rx = frame_word(&ram, context.regs, FRAME_RX)?.convert();
+    let sp = frame_word(&ram, context.regs, FRAME_SP)?.convert();
+    if rx + words > sp {
+        return Err(Errors {
+            msg: format!("No hay {} palabras libres en la pila", words),
+            cod: Interrups::StackOverflow,
+        });
+    }
+
+    ram.writeMemory(
+        context.regs.sp.convert() + FRAME_RX,
+        Palabra::new(&convert_to_string_format_pal(rx + words)).unwrap(),
+    )?;
+    Ok(Syscall_Action::Value(rx - rb))
+}
+
+fn semaphore_mut<'a>(
+    semaphores: &'a mut Vec<Semaphore>,
+    id: i32,
+) -> Result<&'a mut Semaphore, Errors> {
+    convert_option_result(
+        semaphores.iter_mut().find(|s| s.id == id),
+        format!("No existe el semaforo {}", id),
+        Interrups::CallSys,
+    )
+}
+
+//Crea un semaforo con el valor inicial del argumento y devuelve su id
+fn sys_sem_create(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    if args[0] < 0 {
+        return Err(Errors {
+            msg: format!("El valor inicial {} del semaforo es negativo", args[0]),
+            cod: Interrups::CallSys,
+        });
+    }
+
+    let mut semaphores = context.semaphores.lock().unwrap();
+    let id = semaphores.last().map_or(0, |s| s.id + 1);
+    semaphores.push(Semaphore {
+        id,
+        value: args[0],
+        waiting: VecDeque::new(),
+    });
+    Ok(Syscall_Action::Value(id))
+}
+
+//Si el valor es 0 el proceso se encola y queda bloqueado hasta un signal
+fn sys_sem_wait(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let mut semaphores = context.semaphores.lock().unwrap();
+    let sem = semaphore_mut(&mut semaphores, args[0])?;
+    if sem.value > 0 {
+        sem.value -= 1;
+        return Ok(Syscall_Action::Value(0));
+    }
+
+    let program = &mut context.table[context.pos];
+    sem.waiting.push_back(program.pid);
+    program.sem_wait = Some(sem.id);
+    Ok(Syscall_Action::Block)
+}
+
+//Despierta al primero que sigue esperando, si no hay nadie sube el valor
+fn sys_sem_signal(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let mut semaphores = context.semaphores.lock().unwrap();
+    let sem = semaphore_mut(&mut semaphores, args[0])?;
+
+    //Los que se terminaron o se volvieron a correr mientras esperaban se saltean
+    while let Some(pid) = sem.waiting.pop_front() {
+        let table = &mut *context.table;
+        if let Some(pos) = table.iter().position(|p| {
+            p.pid == pid && p.state == State_Process::Blocked && p.sem_wait == Some(sem.id)
+        }) {
+            table[pos].sem_wait = None;
+            set_ready(table, pos);
+            return Ok(Syscall_Action::Value(0));
+        }
+    }
+
+    sem.value += 1;
+    Ok(Syscall_Action::Value(0))
+}
+
+//Los nombres de archivo que usan los programas son numeros
+fn sys_open(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let name = args[0].to_string();
+    let entry = {
+        let mut disk = context.disk.lock().unwrap();
+        match File_System::find(&mut disk, &name)? {
+            Some(entry) => entry,
+            None => File_System::create(&mut disk, &name)?,
+        }
+    };
+
+    let program = &mut context.table[context.pos];
+    let open_file = Some(Open_File { entry, pos: 0 });
+    match program.open_files.iter().position(|f| f.is_none()) {
+        Some(fd) => {
+            program.open_files[fd] = open_file;
+            Ok(Syscall_Action::Value(fd as i32))
+        }
+        None => {
+            program.open_files.push(open_file);
+            Ok(Syscall_Action::Value(program.open_files.len() as i32 - 1))
+        }
+    }
+}
+
+fn open_file_mut(program: &mut Programs, fd: i32) -> Result<&mut Open_File, Errors> {
+    convert_option_result(
+        program
+            .open_files
+            .get_mut(fd.max(0) as usize)
+            .filter(|_| fd >= 0)
+            .and_then(|f| f.as_mut()),
+        format!("El descriptor {} no esta abierto", fd),
+        Interrups::EndIO,
+    )
+}
+
+//Lee la siguiente palabra del archivo, -1 en el fin del archivo
+fn sys_file_read(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let open_file = open_file_mut(&mut context.table[context.pos], args[0])?;
+    let mut disk = context.disk.lock().unwrap();
+    match File_System::read_word(&mut disk, open_file.entry, open_file.pos)? {
+        Some(pal) => {
+            open_file.pos += 1;
+            Ok(Syscall_Action::Value(pal.convert()))
+        }
+        None => Ok(Syscall_Action::Value(-1)),
+    }
+}
+
+//Argumento 0 el descriptor y 1 el valor
+fn sys_file_write(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let pal = Palabra::new(&convert_to_string_format_pal(args[1])).unwrap();
+    let open_file = open_file_mut(&mut context.table[context.pos], args[0])?;
+    let mut disk = context.disk.lock().unwrap();
+    File_System::write_word(&mut disk, open_file.entry, open_file.pos, pal)?;
+    open_file.pos += 1;
+    Ok(Syscall_Action::Value(0))
+}
+
+fn sys_close(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let program = &mut context.table[context.pos];
+    open_file_mut(program, args[0])?;
+    program.open_files[args[0] as usize] = None;
+    Ok(Syscall_Action::Value(0))
+}
+
+//No se borra un archivo que tenga abierto algun proceso
+fn sys_delete(context: &mut Syscall_Context, args: &[i32]) -> Result<Syscall_Action, Errors> {
+    let name = args[0].to_string();
+    let mut disk = context.disk.lock().unwrap();
+    let entry = File_System::find(&mut disk, &name)?;
+    let is_open = context.table.iter().any(|p| {
+        p.open_files
+            .iter()
+            .any(|f| f.is_some_and(|f| Some(f.entry) == entry))
+    });
+    if is_open {
+        return Err(Errors {
+            msg: format!("El archivo {} esta abierto", name),
+            cod: Interrups::EndIO,
+        });
+    }
+
+    File_System::delete(&mut disk, &name)?;
+    Ok(Syscall_Action::Value(0))
+}
+
+//Trae la pagina que falta a un marco libre, si no hay marcos libres reemplaza la pagina mas vieja
+//del mismo proceso (FIFO local) sin tocar las de la pila ni la de la instrucción que fallo. Si no
+//queda otra pagina para reemplazar el proceso termina, si no la instrucción fallaria para siempre
+pub fn page_fault(
+    ram: Arc<Mutex<Ram>>,
+    external_int: Arc<Mutex<External_interrupt>>,
+    table_process: Arc<Mutex<Vec<Programs>>>,
+) -> ContinueOrBreak {
+    let (pid, page, pc_page) = {
+        let lock_int = external_int.lock().unwrap();
+        (
+            lock_int.fault_pid,
+            lock_int.fault_page,
+            lock_int.fault_pc_page,
+        )
+    };
+    println!("Fallo de pagina {page} del proceso {pid}");
+
+    let mut table = table_process.lock().unwrap();
+    let frame = Memory_Manager::free_frames(&table).first().copied();
+
+    let pos = match table.iter().position(|p| p.pid == pid) {
+        Some(pos) => pos,
+        None => return ContinueOrBreak::Break,
+    };
+    let program = &mut table[pos];
+    let mut ram = ram.lock().unwrap();
+
+    let frame = match frame {
+        Some(frame) => frame,
+        None => {
+            let first_stack_page = program.stack_base() / PAGE_SIZE;
+            let victim = program
+                .page_frames
+                .iter()
+                .map(|&(p, _)| p)
+                .find(|&p| p < first_stack_page && p != pc_page);
+
+            match victim.map(|victim| evict_page(program, victim, &mut ram)) {
+                Some(Ok(frame)) => frame,
+                _ => {
+                    println!("No hay marcos libres para el proceso {pid}");
+                    return ContinueOrBreak::Break;
+                }
+            }
+        }
+    };
+
+    match load_page(program, page, frame, &mut ram) {
+        Ok(()) => ContinueOrBreak::Continue,
+        Err(_) => ContinueOrBreak::Break,
     }
-    ContinueOrBreak::Continue
 }
 
 pub fn cod_int_inv() -> ContinueOrBreak {
@@ -145,6 +934,10 @@ pub fn handle_interrupt(
     cod_int: Interrups,
     ram: Arc<Mutex<Ram>>,
     external_int: Arc<Mutex<External_interrupt>>,
+    table_process: Arc<Mutex<Vec<Programs>>>,
+    disk: Arc<Mutex<Disk>>,
+    timer: Arc<Mutex<Timer>>,
+    semaphores: Arc<Mutex<Vec<Semaphore>>>,
 ) -> ContinueOrBreak {
     match cod_int {
         Interrups::Overflow => {
@@ -185,7 +978,7 @@ pub fn handle_interrupt(
                 lock_int.int_io = false;
             }
 
-            end_io()
+            end_io(external_int, table_process)
         }
         Interrups::Clock => {
             {
@@ -201,7 +994,15 @@ pub fn handle_interrupt(
                 lock_int.int_call_sys = false;
             }
 
-            call_sys(regs, ram, external_int)
+            call_sys(
+                regs,
+                ram,
+                external_int,
+                table_process,
+                disk,
+                timer,
+                semaphores,
+            )
         }
         Interrups::CodIntInv => {
             {
@@ -219,5 +1020,29 @@ pub fn handle_interrupt(
 
             cod_call_sys_inv()
         }
+        Interrups::StackOverflow => {
+            {
+                let mut lock_int = external_int.lock().unwrap();
+                lock_int.int_stack_overflow = false;
+            }
+
+            stack_overflow()
+        }
+        Interrups::PageFault => {
+            {
+                let mut lock_int = external_int.lock().unwrap();
+                lock_int.int_page_fault = false;
+            }
+
+            page_fault(ram, external_int, table_process)
+        }
+        Interrups::IoError => {
+            {
+                let mut lock_int = external_int.lock().unwrap();
+                lock_int.int_io_error = false;
+            }
+
+            io_error(external_int, table_process)
+        }
     }
 }
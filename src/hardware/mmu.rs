@@ -0,0 +1,120 @@
+use crate::{
+    hardware::{allocator::USER_MEM_START, interrupts::Interrups, ram::Ram},
+    utils::Errors,
+};
+
+pub const PAGE_SIZE: i32 = 10;
+//Marcos del area de usuario, el marco f empieza en 301 + f * PAGE_SIZE
+pub const NUM_FRAMES: i32 = 170;
+//Las tablas de paginas viven en el area del sistema operativo
+pub const PAGE_TABLE_AREA_START: i32 = 100;
+pub const PAGE_TABLE_AREA_END: i32 = 299;
+//Una entrada de la tabla es valido * 1000 + marco, 0 es pagina no cargada
+pub const PAGE_VALID: i32 = 1000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Mmu {
+    //Base y largo de la tabla de paginas del proceso actual, ptbr -1 es sin paginación
+    pub ptbr: i32,
+    pub ptlr: i32,
+    //Pagina que genero el ultimo fallo de pagina
+    pub fault_page: i32,
+}
+
+impl Mmu {
+    pub fn new() -> Self {
+        Mmu {
+            ptbr: -1,
+            ptlr: 0,
+            fault_page: -1,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.ptbr >= 0
+    }
+
+    //Traduce una dirección logica del proceso a la dirección fisica a traves de la tabla de paginas
+    pub fn translate(&mut self, ram: &Ram, logical: i32) -> Result<i32, Errors> {
+        let page = logical / PAGE_SIZE;
+        if logical < 0 || page >= self.ptlr {
+            return Err(Errors {
+                msg: format!("Dirección logica {} fuera del proceso", logical),
+                cod: Interrups::DirInv,
+            });
+        }
+
+        let entry = ram.readMemory(self.ptbr + page)?.convert();
+        if entry < PAGE_VALID {
+            self.fault_page = page;
+            return Err(Errors {
+                msg: format!("Fallo de pagina {}", page),
+                cod: Interrups::PageFault,
+            });
+        }
+
+        Ok(frame_address(entry % PAGE_VALID) + logical % PAGE_SIZE)
+    }
+}
+
+pub fn frame_address(frame: i32) -> i32 {
+    USER_MEM_START + frame * PAGE_SIZE
+}
+
+pub fn pages_for(size: i32) -> i32 {
+    (size + PAGE_SIZE - 1) / PAGE_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hardware::architecture::Palabra, utils::convert_to_string_format_pal};
+
+    //Tabla de 3 paginas en 100: la 0 en el marco 5, la 1 sin cargar y la 2 en el marco 0
+    fn mmu_with_table(ram: &mut Ram) -> Mmu {
+        for (page, entry) in [PAGE_VALID + 5, 0, PAGE_VALID].iter().enumerate() {
+            let pal = Palabra::new(&convert_to_string_format_pal(*entry)).unwrap();
+            ram.writeMemory(PAGE_TABLE_AREA_START + page as i32, pal)
+                .unwrap();
+        }
+        let mut mmu = Mmu::new();
+        mmu.ptbr = PAGE_TABLE_AREA_START;
+        mmu.ptlr = 3;
+        mmu
+    }
+
+    #[test]
+    fn traduce_por_la_tabla_de_paginas() {
+        let mut ram = Ram::new();
+        let mut mmu = mmu_with_table(&mut ram);
+        assert!(mmu.enabled());
+        assert_eq!(mmu.translate(&ram, 0).unwrap(), frame_address(5));
+        assert_eq!(mmu.translate(&ram, 7).unwrap(), 351 + 7);
+        assert_eq!(mmu.translate(&ram, 23).unwrap(), USER_MEM_START + 3);
+    }
+
+    #[test]
+    fn pagina_no_cargada_es_fallo_de_pagina() {
+        let mut ram = Ram::new();
+        let mut mmu = mmu_with_table(&mut ram);
+        let err = mmu.translate(&ram, 14).unwrap_err();
+        assert_eq!(err.cod, Interrups::PageFault);
+        assert_eq!(mmu.fault_page, 1);
+    }
+
+    #[test]
+    fn fuera_de_la_tabla_es_direccionamiento_invalido() {
+        let mut ram = Ram::new();
+        let mut mmu = mmu_with_table(&mut ram);
+        assert_eq!(mmu.translate(&ram, 30).unwrap_err().cod, Interrups::DirInv);
+        assert_eq!(mmu.translate(&ram, -1).unwrap_err().cod, Interrups::DirInv);
+        assert_eq!(mmu.fault_page, -1);
+    }
+
+    #[test]
+    fn paginas_necesarias_redondean_para_arriba() {
+        assert_eq!(pages_for(1), 1);
+        assert_eq!(pages_for(PAGE_SIZE), 1);
+        assert_eq!(pages_for(PAGE_SIZE + 1), 2);
+    }
+}
@@ -1,8 +1,17 @@
+pub mod allocator;
 pub mod architecture;
+pub mod boot;
+pub mod console;
 pub mod cpu;
 pub mod disk;
+pub mod disk_scheduler;
 pub mod dma;
+pub mod filesystem;
 pub mod instructions;
 pub mod interrupts;
+pub mod mmu;
 pub mod ram;
 pub mod registers;
+pub mod scheduler;
+pub mod swap;
+pub mod timer;
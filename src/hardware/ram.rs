@@ -37,6 +37,21 @@ impl Ram {
         Ok(())
     }
 
+    //Pone en cero el rango de memoria [pos_init, pos_end]
+    pub fn clear(&mut self, pos_init: i32, pos_end: i32) -> Result_op {
+        if pos_init < 301 || pos_end >= 2001 || pos_init > pos_end {
+            return Err(Errors {
+                msg: String::from("Rango de memoria invalido"),
+                cod: Interrups::DirInv,
+            });
+        }
+
+        for pos in pos_init..=pos_end {
+            self.writeMemory(pos, Palabra::new("00000000").unwrap())?;
+        }
+        Ok(())
+    }
+
     pub fn is_empty(&self, pos_init: i32, pos_end: i32) -> Result<bool, Errors> {
         let mut pos = pos_init;
 
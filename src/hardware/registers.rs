@@ -93,6 +93,8 @@ pub struct Registros {
     pub rb: Palabra,
     pub rl: Palabra,
     pub rx: Palabra,
+    //Inicio del segmento de datos, el código va de rb a rd y los datos de rd a rx
+    pub rd: Palabra,
     pub sp: Palabra,
     pub psw: Pws,
     pub ac: Palabra,
@@ -107,6 +109,7 @@ impl Registros {
             rb: Palabra::new("00000000").unwrap(),
             rl: Palabra::new("00000000").unwrap(),
             rx: Palabra::new("00000000").unwrap(),
+            rd: Palabra::new("00000000").unwrap(),
             sp: Palabra::new("00000000").unwrap(),
             psw: Pws::new(),
             ac: Palabra::new("00000000").unwrap(),
@@ -206,6 +209,27 @@ impl Registros {
         self.rx = pal;
         Ok(())
     }
+    pub fn set_rd(&mut self, pal: Palabra) -> Result_op {
+        if pal.convert() > 2000 {
+            let err = Errors {
+                msg: String::from("Dirección de memoria invalida"),
+                cod: Interrups::Overflow,
+            };
+
+            return Err(err);
+        }
+        if pal.convert() < 0 {
+            let err = Errors {
+                msg: String::from(" Dirección de memoria invalida"),
+                cod: Interrups::Underflow,
+            };
+
+            return Err(err);
+        }
+
+        self.rd = pal;
+        Ok(())
+    }
     pub fn set_sp(&mut self, pal: Palabra) -> Result_op {
         if pal.convert() > 2000 {
             let err = Errors {
@@ -0,0 +1,247 @@
+use std::fmt::Debug;
+
+use crate::{Programs, State_Process};
+
+//Elecciones del planificador entre cada vez que mlfq sube todos los procesos al primer nivel
+const MLFQ_BOOST_PERIOD: u32 = 10;
+
+//Politica de planificación que usa el cpu para elegir el siguiente proceso listo
+pub trait Scheduler: Send + Debug {
+    fn name(&self) -> String;
+
+    //Elige de la tabla de procesos el siguiente proceso listo, devuelve su posición
+    fn select(&mut self, table_process: &Vec<Programs>) -> Option<usize>;
+
+    //Quantum que se le da al proceso elegido, None si la politica no expropia por reloj
+    fn quantum(&self, program: &Programs, base_quantum: u32) -> Option<u32>;
+
+    //Aviso de que al proceso se le vencio el quantum
+    fn quantum_expired(&mut self, program: &mut Programs) {}
+
+    //Aviso de que se va a elegir el siguiente proceso, la politica puede ajustar la tabla antes
+    fn dispatching(&mut self, table_process: &mut Vec<Programs>) {}
+}
+
+//Recorre los procesos listos y devuelve la posición del menor según la clave, empatando por orden de llegada a listo
+fn select_min_by_key<K: Ord>(
+    table_process: &Vec<Programs>,
+    key: impl Fn(&Programs) -> K,
+) -> Option<usize> {
+    table_process
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.state == State_Process::Ready)
+        .min_by_key(|(_, p)| (key(p), p.ready_since))
+        .map(|(pos, _)| pos)
+}
+
+#[derive(Debug)]
+pub struct Round_Robin;
+
+impl Scheduler for Round_Robin {
+    fn name(&self) -> String {
+        "rr".to_string()
+    }
+
+    fn select(&mut self, table_process: &Vec<Programs>) -> Option<usize> {
+        select_min_by_key(table_process, |_| 0)
+    }
+
+    fn quantum(&self, program: &Programs, base_quantum: u32) -> Option<u32> {
+        Some(base_quantum)
+    }
+}
+
+//Elige igual que round robin, el que llego antes a listo, pero no expropia
+#[derive(Debug)]
+pub struct Fcfs;
+
+impl Scheduler for Fcfs {
+    fn name(&self) -> String {
+        "fcfs".to_string()
+    }
+
+    fn select(&mut self, table_process: &Vec<Programs>) -> Option<usize> {
+        Round_Robin.select(table_process)
+    }
+
+    fn quantum(&self, program: &Programs, base_quantum: u32) -> Option<u32> {
+        None
+    }
+}
+
+//Trabajo mas corto primero, el largo del trabajo es el numero de palabras del encabezado del programa
+#[derive(Debug)]
+pub struct Sjf;
+
+impl Scheduler for Sjf {
+    fn name(&self) -> String {
+        "sjf".to_string()
+    }
+
+    fn select(&mut self, table_process: &Vec<Programs>) -> Option<usize> {
+        select_min_by_key(table_process, |p| p.num_instruccions)
+    }
+
+    fn quantum(&self, program: &Programs, base_quantum: u32) -> Option<u32> {
+        None
+    }
+}
+
+//Prioridad estatica, el menor numero es la mayor prioridad
+#[derive(Debug)]
+pub struct Priority;
+
+impl Scheduler for Priority {
+    fn name(&self) -> String {
+        "prioridad".to_string()
+    }
+
+    fn select(&mut self, table_process: &Vec<Programs>) -> Option<usize> {
+        select_min_by_key(table_process, |p| p.priority)
+    }
+
+    fn quantum(&self, program: &Programs, base_quantum: u32) -> Option<u32> {
+        None
+    }
+}
+
+//Colas multinivel con retroalimentación, cada nivel duplica el quantum del anterior. Cada
+//MLFQ_BOOST_PERIOD elecciones todos vuelven al primer nivel para que los de abajo no se mueran de hambre
+#[derive(Debug)]
+pub struct Mlfq {
+    pub levels: u8,
+    //Elecciones desde el ultimo ascenso
+    pub dispatches: u32,
+}
+
+impl Scheduler for Mlfq {
+    fn name(&self) -> String {
+        format!("mlfq ({} niveles)", self.levels)
+    }
+
+    fn select(&mut self, table_process: &Vec<Programs>) -> Option<usize> {
+        select_min_by_key(table_process, |p| p.queue_level)
+    }
+
+    fn quantum(&self, program: &Programs, base_quantum: u32) -> Option<u32> {
+        Some(base_quantum.max(1) << program.queue_level.min(self.levels - 1))
+    }
+
+    //Si el proceso gasto todo su quantum baja de nivel
+    fn quantum_expired(&mut self, program: &mut Programs) {
+        if program.queue_level + 1 < self.levels {
+            program.queue_level += 1;
+        }
+    }
+
+    fn dispatching(&mut self, table_process: &mut Vec<Programs>) {
+        self.dispatches += 1;
+        if self.dispatches < MLFQ_BOOST_PERIOD {
+            return;
+        }
+
+        self.dispatches = 0;
+        for program in table_process.iter_mut() {
+            program.queue_level = 0;
+        }
+    }
+}
+
+pub fn scheduler_by_name(name: &str) -> Option<Box<dyn Scheduler>> {
+    match name {
+        "rr" => Some(Box::new(Round_Robin)),
+        "fcfs" => Some(Box::new(Fcfs)),
+        "sjf" => Some(Box::new(Sjf)),
+        "prioridad" => Some(Box::new(Priority)),
+        "mlfq" => Some(Box::new(Mlfq {
+            levels: 3,
+            dispatches: 0,
+        })),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Proceso listo con su orden de llegada, largo, prioridad y nivel de mlfq
+    fn ready(pid: i32, ready_since: u64, largo: i32, priority: i32, level: u8) -> Programs {
+        let mut program = Programs::new();
+        program.pid = pid;
+        program.state = State_Process::Ready;
+        program.ready_since = ready_since;
+        program.num_instruccions = largo;
+        program.priority = priority;
+        program.queue_level = level;
+        program
+    }
+
+    fn table() -> Vec<Programs> {
+        let mut blocked = ready(0, 0, 1, 0, 0);
+        blocked.state = State_Process::Blocked;
+        vec![
+            blocked,
+            ready(1, 3, 30, 2, 0),
+            ready(2, 1, 50, 5, 2),
+            ready(3, 2, 10, 1, 1),
+        ]
+    }
+
+    fn selected_pid(scheduler: &mut dyn Scheduler, table: &mut Vec<Programs>) -> Option<i32> {
+        scheduler.dispatching(table);
+        scheduler.select(table).map(|pos| table[pos].pid)
+    }
+
+    #[test]
+    fn rr_y_fcfs_eligen_el_primero_en_llegar_a_listo() {
+        let mut table = table();
+        assert_eq!(selected_pid(&mut Round_Robin, &mut table), Some(2));
+        assert_eq!(selected_pid(&mut Fcfs, &mut table), Some(2));
+        assert_eq!(Round_Robin.quantum(&table[1], 1), Some(1));
+        assert_eq!(Fcfs.quantum(&table[1], 1), None);
+    }
+
+    #[test]
+    fn sjf_elige_el_trabajo_mas_corto() {
+        assert_eq!(selected_pid(&mut Sjf, &mut table()), Some(3));
+    }
+
+    #[test]
+    fn prioridad_elige_el_menor_valor() {
+        assert_eq!(selected_pid(&mut Priority, &mut table()), Some(3));
+    }
+
+    #[test]
+    fn sin_listos_no_elige() {
+        let mut table = table();
+        for program in table.iter_mut() {
+            program.state = State_Process::Terminated;
+        }
+        assert_eq!(selected_pid(&mut Round_Robin, &mut table), None);
+    }
+
+    #[test]
+    fn mlfq_baja_de_nivel_y_sube_a_todos_cada_periodo() {
+        let mut mlfq = Mlfq {
+            levels: 3,
+            dispatches: 0,
+        };
+        let mut table = table();
+        assert_eq!(selected_pid(&mut mlfq, &mut table), Some(1));
+        assert_eq!(mlfq.quantum(&table[3], 1), Some(2));
+
+        mlfq.quantum_expired(&mut table[1]);
+        mlfq.quantum_expired(&mut table[1]);
+        mlfq.quantum_expired(&mut table[1]);
+        assert_eq!(table[1].queue_level, 2);
+        assert_eq!(selected_pid(&mut mlfq, &mut table), Some(3));
+
+        for _ in 2..MLFQ_BOOST_PERIOD - 1 {
+            mlfq.dispatching(&mut table);
+        }
+        assert_eq!(selected_pid(&mut mlfq, &mut table), Some(2));
+        assert!(table.iter().all(|p| p.queue_level == 0));
+    }
+}
@@ -0,0 +1,328 @@
+use std::sync::{Arc, Mutex, mpsc::Sender};
+
+use crate::{
+    Programs, State_Process,
+    hardware::{
+        allocator::Memory_Manager,
+        boot::BOOT_CIL,
+        disk::{CILINDROS, Disk, PISTAS, SECTORES},
+        dma::{Dma_Config, kernel_transfer},
+        filesystem::{FS_CIL_END, FS_CIL_START},
+        interrupts::External_interrupt,
+        ram::Ram,
+    },
+    utils::{Errors, Result_op, convert_option_result, convert_result},
+};
+
+use super::interrupts::Interrups;
+
+//Lo que usa el swap para pedir transferencias al dma y esperar su fin de E/S
+pub struct Swap_Devices<'a> {
+    pub sender: &'a Sender<Dma_Config>,
+    pub external_int: &'a Arc<Mutex<External_interrupt>>,
+    pub disk: &'a Arc<Mutex<Disk>>,
+}
+
+//Zona de intercambio del disco, va de cil_start a cil_end inclusive
+#[derive(Debug)]
+pub struct Swap_Manager {
+    pub cil_start: i8,
+    pub cil_end: i8,
+    pub swap_outs: u32,
+    pub swap_ins: u32,
+}
+
+impl Swap_Manager {
+    pub fn new() -> Self {
+        Swap_Manager {
+            cil_start: 9,
+            cil_end: 10,
+            swap_outs: 0,
+            swap_ins: 0,
+        }
+    }
+
+    //Sectores de la zona de intercambio
+    pub fn capacity(&self) -> i32 {
+        (self.cil_end - self.cil_start + 1) as i32 * PISTAS as i32 * SECTORES as i32
+    }
+
+    //Solo se puede mover la zona de intercambio si no hay procesos en ella
+    pub fn set_region(
+        &mut self,
+        table_process: &Vec<Programs>,
+        cil_start: i8,
+        cil_end: i8,
+    ) -> Result_op {
+        if cil_start < 0 || cil_end >= CILINDROS || cil_start > cil_end {
+            return Err(Errors {
+                msg: format!(
+                    "Zona de intercambio invalida, los cilindros van de 0 a {}",
+                    CILINDROS - 1
+                ),
+                cod: Interrups::DirInv,
+            });
+        }
+        if cil_start <= FS_CIL_END && cil_end >= FS_CIL_START {
+            return Err(Errors {
+                msg: format!(
+                    "Los cilindros {} a {} son del sistema de archivos",
+                    FS_CIL_START, FS_CIL_END
+                ),
+                cod: Interrups::DirInv,
+            });
+        }
+        if cil_start <= BOOT_CIL && cil_end >= BOOT_CIL {
+            return Err(Errors {
+                msg: format!("El cilindro {} tiene la pista de arranque", BOOT_CIL),
+                cod: Interrups::DirInv,
+            });
+        }
+        if table_process.iter().any(|p| p.swap_sector >= 0) {
+            return Err(Errors {
+                msg: "Hay procesos en la zona de intercambio".to_string(),
+                cod: Interrups::DirInv,
+            });
+        }
+
+        self.cil_start = cil_start;
+        self.cil_end = cil_end;
+        Ok(())
+    }
+
+    //Sector lineal de la zona de intercambio a (cilindro, pista, sector)
+    pub fn sector_address(&self, sector: i32) -> (i8, i8, i8) {
+        let per_cil = PISTAS as i32 * SECTORES as i32;
+        (
+            self.cil_start + (sector / per_cil) as i8,
+            ((sector % per_cil) / SECTORES as i32) as i8,
+            (sector % SECTORES as i32) as i8,
+        )
+    }
+
+    //Primer lugar libre de la zona de intercambio donde entran size palabras
+    pub fn allocate(&self, table_process: &Vec<Programs>, size: i32) -> Option<i32> {
+        let mut used: Vec<(i32, i32)> = table_process
+            .iter()
+            .filter(|p| p.swap_sector >= 0)
+            .map(|p| {
+                (
+                    p.swap_sector,
+                    p.swap_sector + Memory_Manager::size_program(p),
+                )
+            })
+            .collect();
+        used.sort();
+
+        let mut pos = 0;
+        for (start, end) in used {
+            if start - pos >= size {
+                return Some(pos);
+            }
+            pos = pos.max(end);
+        }
+        if self.capacity() - pos >= size {
+            return Some(pos);
+        }
+        None
+    }
+
+    //Pedido al dma para mover la imagen entera del proceso entre pos_men y la zona de intercambio
+    fn transfer(&self, program: &Programs, sector: i32, pos_men: i32, modo: i8) -> Dma_Config {
+        let mut config = Dma_Config::new();
+        (
+            config.cil_acceder,
+            config.pista_acceder,
+            config.sector_acceder,
+        ) = self.sector_address(sector);
+        config.pos_men = pos_men;
+        config.modo = modo;
+        config.pid = program.pid;
+        config.count = Memory_Manager::size_program(program);
+        config
+    }
+
+    //Copia la imagen del proceso al disco por la cola del dma y libera su memoria cuando termina
+    pub fn swap_out(
+        &mut self,
+        table_process: &mut Vec<Programs>,
+        pos: usize,
+        ram: &Arc<Mutex<Ram>>,
+        devices: &Swap_Devices,
+    ) -> Result_op {
+        let size = Memory_Manager::size_program(&table_process[pos]);
+        let sector = convert_option_result(
+            self.allocate(table_process, size),
+            "Error no hay lugar en la zona de intercambio".to_string(),
+            Interrups::EndIO,
+        )?;
+
+        let program = &mut table_process[pos];
+        let config = self.transfer(program, sector, program.pos_start_mem, 1);
+        kernel_transfer(devices.sender, devices.external_int, devices.disk, config)?;
+
+        ram.lock()
+            .unwrap()
+            .clear(program.pos_start_mem, program.pos_start_mem + size - 1)?;
+
+        program.swap_sector = sector;
+        self.swap_outs += 1;
+        println!(
+            "-> Se saco a swap el proceso {} (pid {}), {} palabras",
+            program.name, program.pid, size
+        );
+        Ok(())
+    }
+
+    //Pide al dma traer el proceso del disco a la dirección dir y lo reubica ahi. El proceso queda
+    //bloqueado hasta que el fin de E/S de la transferencia lo pase a listo
+    pub fn swap_in(
+        &mut self,
+        program: &mut Programs,
+        dir: i32,
+        devices: &Swap_Devices,
+    ) -> Result_op {
+        let mut config = self.transfer(program, program.swap_sector, dir, 0);
+        config.sent = devices.disk.lock().unwrap().now();
+        convert_result(
+            devices.sender.send(config),
+            "Error al enviar orden dma".to_string(),
+            Interrups::InstInv,
+        )?;
+
+        Memory_Manager::relocate(program, dir - program.pos_start_mem)?;
+        program.swap_sector = -1;
+        program.pending_io += 1;
+        program.state = State_Process::Blocked;
+        self.swap_ins += 1;
+        println!(
+            "-> Se pidio traer de swap el proceso {} (pid {}) a la dirección {}",
+            program.name, program.pid, dir
+        );
+        Ok(())
+    }
+
+    //Busca lugar para size palabras: primero un hueco, despues compactando y por ultimo sacando
+    //procesos a swap. Nunca saca al proceso keep_pid. Devuelve None si no se puede hacer lugar
+    pub fn make_room(
+        &mut self,
+        memory_manager: &mut Memory_Manager,
+        table_process: &mut Vec<Programs>,
+        ram: &Arc<Mutex<Ram>>,
+        devices: &Swap_Devices,
+        size: i32,
+        keep_pid: i32,
+    ) -> Result<Option<i32>, Errors> {
+        loop {
+            if let Some(dir) = memory_manager.allocate(table_process, size) {
+                return Ok(Some(dir));
+            }
+
+            if Memory_Manager::free_memory(table_process) >= size {
+                println!("-> No hay hueco de {} palabras, compactando memoria", size);
+                Memory_Manager::compact(table_process, ram)?;
+                if let Some(dir) = memory_manager.allocate(table_process, size) {
+                    return Ok(Some(dir));
+                }
+            }
+
+            //La victima es la que va a esperar mas para correr: primero los terminados, despues los nuevos
+            //y por ultimo el ultimo en llegar a listo
+            let victim = table_process
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| {
+                    matches!(
+                        p.state,
+                        State_Process::New | State_Process::Ready | State_Process::Terminated
+                    ) && p.swap_sector < 0
+                        && p.ptbr < 0
+                        && p.pending_io == 0
+                        && p.pid != keep_pid
+                })
+                .max_by_key(|(_, p)| {
+                    (
+                        p.state == State_Process::Terminated,
+                        p.state == State_Process::New,
+                        p.ready_since,
+                    )
+                })
+                .map(|(pos, _)| pos);
+
+            match victim {
+                Some(pos) => self.swap_out(table_process, pos, ram, devices)?,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    pub fn print_swap(&self, table_process: &Vec<Programs>) {
+        let list: Vec<String> = table_process
+            .iter()
+            .filter(|p| p.swap_sector >= 0)
+            .map(|p| {
+                let (cil, pista, sec) = self.sector_address(p.swap_sector);
+                format!("{} (pid {}) en c{} p{} s{}", p.name, p.pid, cil, pista, sec)
+            })
+            .collect();
+
+        println!(
+            "-> Zona de intercambio: cilindros {}..{}, {} sectores",
+            self.cil_start,
+            self.cil_end,
+            self.capacity()
+        );
+        println!("-> Procesos en swap: {}", list.join(", "));
+        println!(
+            "-> Swap-out: {}, swap-in: {}",
+            self.swap_outs, self.swap_ins
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Proceso de size palabras guardado en la zona de intercambio desde sector
+    fn swapped(sector: i32, size: i32) -> Programs {
+        let mut program = Programs::new();
+        program.swap_sector = sector;
+        program.num_instruccions_with_pila = size - 1;
+        program
+    }
+
+    #[test]
+    fn direcciones_de_la_zona_de_intercambio() {
+        let swap = Swap_Manager::new();
+        assert_eq!(swap.capacity(), 2 * PISTAS as i32 * SECTORES as i32);
+        assert_eq!(swap.sector_address(0), (9, 0, 0));
+        assert_eq!(swap.sector_address(SECTORES as i32 + 2), (9, 1, 2));
+        assert_eq!(
+            swap.sector_address(PISTAS as i32 * SECTORES as i32),
+            (10, 0, 0)
+        );
+        assert_eq!(swap.sector_address(swap.capacity() - 1), (10, 10, 100));
+    }
+
+    #[test]
+    fn asigna_el_primer_lugar_libre() {
+        let swap = Swap_Manager::new();
+        let table = vec![swapped(0, 100), swapped(150, 50), Programs::new()];
+        assert_eq!(swap.allocate(&table, 50), Some(100));
+        assert_eq!(swap.allocate(&table, 51), Some(200));
+        assert_eq!(swap.allocate(&table, swap.capacity() - 200), Some(200));
+        assert_eq!(swap.allocate(&table, swap.capacity() - 199), None);
+    }
+
+    #[test]
+    fn la_zona_no_puede_pisar_el_sistema_de_archivos_ni_moverse_ocupada() {
+        let mut swap = Swap_Manager::new();
+        assert!(swap.set_region(&vec![], FS_CIL_END, 6).is_err());
+        assert!(swap.set_region(&vec![], 8, CILINDROS).is_err());
+        assert!(swap.set_region(&vec![swapped(0, 10)], 7, 8).is_err());
+
+        swap.set_region(&vec![], 7, 8).unwrap();
+        assert_eq!(swap.sector_address(0), (7, 0, 0));
+    }
+}
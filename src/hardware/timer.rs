@@ -0,0 +1,98 @@
+//Ticks entre interrupciones de reloj hasta que el kernel programe otro periodo con tti
+const DEFAULT_PERIOD: u32 = 3;
+
+//Señales que el cpu le manda al hilo del temporizador: un ciclo del procesador o que se apaga
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timer_Signal {
+    Cycle,
+    Off,
+}
+
+//Temporizador que corre en su propio hilo, avanza un tick por cada ciclo que le avisa el cpu y lanza
+//la interrupción de reloj cada periodo
+#[derive(Debug)]
+pub struct Timer {
+    //Ticks entre interrupciones de reloj, en 0 el temporizador no interrumpe
+    pub period: u32,
+    //Ticks desde la ultima interrupción
+    pub counter: u32,
+    //Hora del dia: ticks desde que arranco el sistema
+    pub time: u64,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer {
+            period: DEFAULT_PERIOD,
+            counter: 0,
+            time: 0,
+        }
+    }
+
+    //Avanza un tick, devuelve true si se cumplio el periodo y toca interrupción de reloj
+    pub fn tick(&mut self) -> bool {
+        self.time += 1;
+        if self.period == 0 {
+            return false;
+        }
+
+        self.counter += 1;
+        if self.counter >= self.period {
+            self.counter = 0;
+            return true;
+        }
+        false
+    }
+
+    //Adelanta la hora lo que tardo un acceso al disco, no cuenta para el periodo del reloj
+    pub fn advance(&mut self, ticks: u64) {
+        self.time += ticks;
+    }
+
+    //El periodo nuevo empieza a contar desde cero
+    pub fn set_period(&mut self, period: u32) {
+        self.period = period;
+        self.counter = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrumpe_cada_periodo() {
+        let mut timer = Timer::new();
+        let clocks: Vec<bool> = (0..7).map(|_| timer.tick()).collect();
+        assert_eq!(clocks, vec![false, false, true, false, false, true, false]);
+        assert_eq!(timer.time, 7);
+    }
+
+    #[test]
+    fn con_periodo_cero_no_interrumpe_pero_sigue_la_hora() {
+        let mut timer = Timer::new();
+        timer.set_period(0);
+        assert!((0..10).all(|_| !timer.tick()));
+        assert_eq!(timer.time, 10);
+    }
+
+    #[test]
+    fn el_periodo_nuevo_cuenta_desde_cero() {
+        let mut timer = Timer::new();
+        timer.tick();
+        timer.tick();
+        timer.set_period(2);
+        assert!(!timer.tick());
+        assert!(timer.tick());
+    }
+
+    #[test]
+    fn el_disco_adelanta_la_hora_sin_tocar_el_periodo() {
+        let mut timer = Timer::new();
+        timer.tick();
+        timer.advance(40);
+        assert_eq!(timer.time, 41);
+        assert!(!timer.tick());
+        assert!(timer.tick());
+    }
+}
@@ -15,121 +15,258 @@ use hardware::{
 
 use crate::{
     hardware::{
+        allocator::{Memory_Manager, USER_MEM_START},
+        boot::{BOOT_CIL, BOOT_PISTA, Boot, KERNEL_START},
+        console::Console,
         cpu::{
             Cpu, Registers_Cpu_Config, Result_Execute, Result_Execute_program, Result_Instruction,
         },
-        dma::{Dma, Dma_Config, State_Dma},
+        disk_scheduler::disk_scheduler_by_name,
+        dma::{DMA_ERR_NONE, Dma, Dma_Config, Dma_Queue, Dma_Status, State_Dma},
+        filesystem::{File_System, Open_File},
         instructions::{self, Instruction},
-        interrupts::{External_interrupt, handle_interrupt},
+        interrupts::{
+            External_interrupt, PRIORITY, SYSCALLS, Semaphore, VECTOR_SIZE, handle_interrupt,
+            install_vector, print_semaphores, wake_sleepers,
+        },
         ram,
+        scheduler::{Round_Robin, Scheduler, scheduler_by_name},
+        swap::{Swap_Devices, Swap_Manager},
+        timer::{Timer, Timer_Signal},
+    },
+    utils::{
+        convert_to_string_format_pal, init_context_program, linear_search_program_mut,
+        load_program_in_ram, load_program_paged, print_table_process, read_program_file,
+        read_words_file, set_ready, size_program_file, unload_program, write_words_file,
     },
-    utils::{convert_to_string_format_pal, linear_search_program, load_program_in_ram},
 };
 
 enum Mode_Execute {
     normal,
     debbuger,
     off,
+    //Arranca el kernel cargado del disco con sus registros iniciales
+    boot(Registros),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State_Process {
+    New,
+    Ready,
+    Running,
+    Blocked,
+    Terminated,
+}
+
+//Por que termino el proceso
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum End_Reason {
+    None,
+    //Termino con exit, el estado lo dio el programa
+    Exit,
+    //Falla que lo termino y dirección de la instrucción que la causo
+    Fault(Interrups, i32),
+    //Lo termino el usuario con kill
+    Killed,
+}
+
+impl End_Reason {
+    pub fn describe(&self) -> String {
+        match self {
+            End_Reason::None => "sin terminar".to_string(),
+            End_Reason::Exit => "salida normal".to_string(),
+            End_Reason::Fault(kind, dir) => format!("falla {:?} en la dirección {}", kind, dir),
+            End_Reason::Killed => "terminado con kill".to_string(),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Programs {
+    pub pid: i32,
     pub name: String,
     pub num_instruccions_with_pila: i32,
+    //Numero de palabras declarado en el encabezado, lo usa sjf como largo del trabajo
+    pub num_instruccions: i32,
+    //Tamaño de los segmentos de código, datos y pila declarados en el encabezado
+    pub code_size: i32,
+    pub data_size: i32,
+    pub stack_size: i32,
+    //Solo si el encabezado declara .Codigo el código es de solo lectura
+    pub code_protected: bool,
     pub pos_start_mem: i32,
     pub pos_start_program: i32,
+    pub state: State_Process,
+    //Orden de llegada a listo, lo usan los planificadores para desempatar
+    pub ready_since: u64,
+    pub priority: i32,
+    //Nivel de la cola en mlfq
+    pub queue_level: u8,
+    //Contexto guardado del proceso mientras no esta en el procesador
+    pub context: Registros,
+    pub exit_status: i32,
+    pub end_reason: End_Reason,
+    //Contabilidad: instrucciones de usuario ejecutadas, veces que tomo el cpu y pasos esperando en listo
+    pub instructions_executed: u32,
+    pub dispatches: u32,
+    pub wait_time: u32,
+    //Paginación: base de la tabla de paginas en el area del sistema (-1 si es contiguo), cantidad de paginas,
+    //paginas cargadas en orden de llegada con su marco e imagen del proceso para traer paginas
+    pub ptbr: i32,
+    pub pages: i32,
+    pub page_frames: Vec<(i32, i32)>,
+    pub image: Vec<Palabra>,
+    //Primer sector de la zona de intercambio donde esta la imagen del proceso, -1 si esta en memoria
+    pub swap_sector: i32,
+    //Archivos abiertos, el descriptor es la posición en el vector
+    pub open_files: Vec<Option<Open_File>>,
+    //Lo que el proceso escribio en la consola
+    pub output: String,
+    //Tick del temporizador en que se despierta si esta dormido
+    pub sleep_until: Option<u64>,
+    //Operaciones del dma que pidio y todavia no terminaron
+    pub pending_io: u32,
+    //Semaforo en el que esta bloqueado
+    pub sem_wait: Option<i32>,
+    //Estado de la ultima operación del dma que pidio el proceso, es lo que lee con ldmae
+    pub dma_status: Dma_Status,
+    //Registros del dma que el proceso programo con sdma* y la dirección logica de sdmam, se guardan con
+    //su contexto
+    pub dma_temp: Dma_Config,
+    pub dma_logical: i32,
 }
 
 impl Programs {
     pub fn new() -> Self {
         Programs {
+            pid: -1,
             name: "".to_string(),
             num_instruccions_with_pila: -1,
+            num_instruccions: -1,
+            code_size: 0,
+            data_size: 0,
+            code_protected: false,
+            stack_size: 0,
             pos_start_mem: -1,
             pos_start_program: -1,
+            state: State_Process::New,
+            ready_since: 0,
+            priority: 0,
+            queue_level: 0,
+            context: Registros::new(),
+            exit_status: 0,
+            end_reason: End_Reason::None,
+            instructions_executed: 0,
+            dispatches: 0,
+            wait_time: 0,
+            ptbr: -1,
+            pages: 0,
+            page_frames: vec![],
+            image: vec![],
+            swap_sector: -1,
+            open_files: vec![],
+            output: String::new(),
+            sleep_until: None,
+            pending_io: 0,
+            sem_wait: None,
+            dma_status: Dma_Status::new(),
+            dma_temp: Dma_Config::new(),
+            dma_logical: 0,
         }
     }
+
+    //Desplazamiento desde rb donde empieza la pila (rx), despues del código y los datos
+    pub fn stack_base(&self) -> i32 {
+        self.code_size + self.data_size
+    }
 }
 
 fn main() {
     let mut ram = Arc::new(Mutex::new(Ram::new()));
     let mut external_interrupts = Arc::new(Mutex::new(External_interrupt::new()));
     let (tx_dma, rx_dma) = mpsc::channel::<Dma_Config>();
+    //El kernel manda por aca las transferencias del arranque y del swap
+    let tx_dma_kernel = tx_dma.clone();
     let (tx_terminal, rx_terminal) = mpsc::channel::<Result_Execute>();
     let (tx_cpu, rx_cpu) = mpsc::channel::<Registers_Cpu_Config>();
+    let (tx_timer, rx_timer) = mpsc::channel::<Timer_Signal>();
+    let (tx_timer_ack, rx_timer_ack) = mpsc::channel::<()>();
     let mut handles = vec![];
-    let mut table_proccess: Vec<Programs> = vec![];
+    let mut table_proccess = Arc::new(Mutex::new(Vec::<Programs>::new()));
+    let scheduler: Arc<Mutex<Box<dyn Scheduler>>> = Arc::new(Mutex::new(Box::new(Round_Robin)));
+    let memory_manager = Arc::new(Mutex::new(Memory_Manager::new()));
+    let swap_manager = Arc::new(Mutex::new(Swap_Manager::new()));
+    let timer = Arc::new(Mutex::new(Timer::new()));
+    let disk = Arc::new(Mutex::new(Disk::new(Arc::clone(&timer))));
+    let semaphores = Arc::new(Mutex::new(Vec::<Semaphore>::new()));
+
+    //El disco se respalda en una imagen para que los datos queden entre sesiones
+    if let Err(e) = disk.lock().unwrap().mount("input/disk.img") {
+        println!("{}, el disco queda solo en memoria", e.msg);
+    }
 
     {
         let mut men = ram.lock().unwrap();
-        for i in (0..9) {
-            let code_interrupt = Palabra::new(&format!("9{i}000000").to_string()).unwrap();
-            men.writeMemory(i, code_interrupt);
-        }
+        install_vector(&mut men);
     }
 
-    let mut cpu = Cpu::new(Arc::clone(&ram), Arc::clone(&external_interrupts), tx_dma);
+    let mut cpu = Cpu::new(
+        Arc::clone(&ram),
+        Arc::clone(&external_interrupts),
+        tx_dma,
+        tx_timer,
+        rx_timer_ack,
+        Arc::clone(&table_proccess),
+        Arc::clone(&scheduler),
+        Arc::clone(&memory_manager),
+        Arc::clone(&swap_manager),
+        Arc::clone(&disk),
+        Arc::clone(&timer),
+        Arc::clone(&semaphores),
+    );
 
     let cpu_thread = thread::spawn(move || {
         loop {
             cpu.result_last_program.result_program = Result_Execute_program::Succes;
             match rx_cpu.recv() {
                 Ok(cpu_config) => match cpu_config.mode {
-                    Mode_Execute::debbuger => match cpu.have_user_program {
-                        true => {
-                            cpu.result_last_program.dir_inst = cpu.registers.psw.pc;
-                            cpu.step();
-                            cpu.result_last_program.instruction =
-                                cpu.registers.ir.conver_to_palabra();
+                    Mode_Execute::debbuger => {
+                        if !cpu.have_user_program() && !cpu.wait_for_ready() {
+                            cpu.result_last_program.result_program = Result_Execute_program::Error;
 
+                            cpu.result_last_program.result_instruction = Result_Instruction::String(
+                                String::from("No hay programa en ejecución"),
+                            );
                             tx_terminal.send(cpu.result_last_program.clone());
-                            sleep(Duration::from_millis(500));
-
                             continue;
                         }
-                        false => {
-                            if cpu_config.pc == -1 {
-                                cpu.result_last_program.result_program =
-                                    Result_Execute_program::Error;
-
-                                cpu.result_last_program.result_instruction =
-                                    Result_Instruction::String(String::from(
-                                        "No hay programa en ejecución",
-                                    ));
-                                tx_terminal.send(cpu.result_last_program.clone());
-                                continue;
-                            }
 
-                            cpu.registers.set_rb(cpu_config.rb);
-                            cpu.registers.set_rl(cpu_config.rl);
-                            cpu.registers.set_rx(cpu_config.rx);
-                            cpu.registers.set_sp(cpu_config.sp);
-                            cpu.registers.psw.set_pc(cpu_config.pc);
-                            cpu.have_user_program = true;
-                            cpu.result_last_program.dir_inst = cpu.registers.psw.pc;
-                            cpu.step();
-                            cpu.result_last_program.instruction =
-                                cpu.registers.ir.conver_to_palabra();
+                        cpu.unpark_context();
+                        cpu.result_last_program.dir_inst = cpu.registers.psw.pc;
+                        cpu.step();
+                        cpu.result_last_program.instruction = cpu.registers.ir.conver_to_palabra();
+                        cpu.park_context();
 
-                            tx_terminal.send(cpu.result_last_program.clone());
-                            sleep(Duration::from_millis(500));
+                        tx_terminal.send(cpu.result_last_program.clone());
+                        sleep(Duration::from_millis(500));
 
-                            continue;
-                        }
-                    },
+                        continue;
+                    }
                     Mode_Execute::normal => {
-                        cpu.registers.set_rb(cpu_config.rb);
-                        cpu.registers.set_rl(cpu_config.rl);
-                        cpu.registers.set_rx(cpu_config.rx);
-                        cpu.registers.set_sp(cpu_config.sp);
-                        cpu.registers.psw.set_pc(cpu_config.pc);
                         cpu.run();
 
                         tx_terminal.send(cpu.result_last_program.clone());
                         continue;
                     }
+                    Mode_Execute::boot(registers) => {
+                        cpu.boot(registers);
+
+                        tx_terminal.send(cpu.result_last_program.clone());
+                        continue;
+                    }
                     Mode_Execute::off => {
                         println!("--- APAGANDO CPU ---");
+                        cpu.sender_timer.send(Timer_Signal::Off);
                         cpu.dma_temp.state = State_Dma::Off;
                         cpu.sender_dma.send(cpu.dma_temp);
                         break;
@@ -146,36 +283,122 @@ fn main() {
 
     let mem_dma = Arc::clone(&ram);
     let external_interrupt_dma = Arc::clone(&external_interrupts);
+    let disk_dma = Arc::clone(&disk);
+    let dma_queue = Arc::new(Mutex::new(Dma_Queue::new()));
+    let queue_dma = Arc::clone(&dma_queue);
 
     let dma_thread = thread::spawn(move || {
         let mut dma = Dma::new();
-        let mut disk = Disk::new();
         loop {
-            match rx_dma.recv() {
+            //Con pedidos en cola no se espera, solo se juntan los que hayan llegado antes de elegir
+            let message = if queue_dma.lock().unwrap().pending.is_empty() {
+                rx_dma.recv().map_err(|_| true)
+            } else {
+                rx_dma
+                    .try_recv()
+                    .map_err(|e| e == mpsc::TryRecvError::Disconnected)
+            };
+
+            match message {
                 Ok(dma_config) => {
                     if dma_config.state == State_Dma::Off {
                         println!("--- APAGANDO DMA ---");
+                        let disk = disk_dma.lock().unwrap();
+                        if disk.path.is_some() {
+                            if let Err(e) = disk.save() {
+                                println!("{}", e.msg);
+                            }
+                        }
                         break;
                     }
 
+                    queue_dma.lock().unwrap().push(dma_config);
+                }
+                Err(false) => {
+                    let next = {
+                        let mut queue = queue_dma.lock().unwrap();
+                        let mut disk = disk_dma.lock().unwrap();
+                        queue.next_request(&mut disk)
+                    };
+                    let (dma_config, ticks) = match next {
+                        Some((request, ticks)) => (request.config, ticks),
+                        None => continue,
+                    };
+
                     dma.pista_acceder = dma_config.pista_acceder;
                     dma.cil_acceder = dma_config.cil_acceder;
                     dma.sector_acceder = dma_config.sector_acceder;
                     dma.pos_men = dma_config.pos_men;
                     dma.modo = dma_config.modo;
+                    dma.pid = dma_config.pid;
+                    dma.count = dma_config.count;
+                    external_interrupt_dma.lock().unwrap().dma_status = Dma_Status {
+                        estado: State_Dma::Busy,
+                        cod_error: DMA_ERR_NONE,
+                        pid: dma.pid,
+                    };
+
+                    //El acceso tarda sin tener tomado el disco, asi la cpu sigue mientras llegan otros pedidos
+                    sleep(Disk::wait_ticks(ticks));
 
-                    dma.execute(&mut disk, &mem_dma, &external_interrupt_dma);
+                    let result = {
+                        let mut disk = disk_dma.lock().unwrap();
+                        dma.execute(&mut disk, &mem_dma, &external_interrupt_dma)
+                    };
+
+                    //Si la operación falla se lanza el error de E/S, que tambien despierta al proceso
+                    let mut ext = external_interrupt_dma.lock().unwrap();
+                    match result {
+                        Ok(()) => ext.dma_status.estado = State_Dma::Succes,
+                        Err(err) => {
+                            println!("Error en la operación dma: {}", err.msg);
+                            let cod_error = Dma_Status::error_code(&err);
+                            ext.dma_status.estado = State_Dma::Error;
+                            ext.dma_status.cod_error = cod_error;
+                            ext.int_io_error = true;
+                            ext.io_error_pids.push((dma.pid, cod_error));
+                        }
+                    }
                 }
-                Err(_) => {
-                    println!("Error al recibir orden dma")
+                Err(true) => {
+                    println!("Error al recibir orden dma");
+                    break;
                 }
             }
         }
     });
     handles.push(dma_thread);
 
-    let exit = false;
-    while !exit {
+    let timer_device = Arc::clone(&timer);
+    let external_interrupt_timer = Arc::clone(&external_interrupts);
+    let table_timer = Arc::clone(&table_proccess);
+
+    //El temporizador corre en su hilo como el dma, cada ciclo del cpu avanza la hora, despierta a los
+    //dormidos y al cumplir el periodo lanza el reloj
+    let timer_thread = thread::spawn(move || {
+        loop {
+            match rx_timer.recv() {
+                Ok(Timer_Signal::Cycle) => {
+                    let (clock, now) = {
+                        let mut timer = timer_device.lock().unwrap();
+                        (timer.tick(), timer.time)
+                    };
+                    if clock {
+                        external_interrupt_timer.lock().unwrap().int_clock = true;
+                    }
+                    wake_sleepers(&mut table_timer.lock().unwrap(), now);
+                    tx_timer_ack.send(());
+                }
+                Ok(Timer_Signal::Off) | Err(_) => {
+                    println!("--- APAGANDO TEMPORIZADOR ---");
+                    break;
+                }
+            }
+        }
+    });
+    handles.push(timer_thread);
+
+    loop {
         //Variables necesarias para recibir el comando
         let mut current_inst = String::new();
         let mut params_inst = String::new();
@@ -221,20 +444,82 @@ fn main() {
                             name_arch = sp.to_string();
                         }
                         //direccion a guardar
-                        1 => dir = sp.parse::<i32>().unwrap(),
+                        1 => dir = sp.parse::<i32>().unwrap_or(-1),
                         _ => (),
                     }
                 }
 
-                if dir == -1 || name_arch == "" {
+                if name_arch == "" || (params_inst.split_whitespace().count() > 1 && dir == -1) {
                     println!("-> Error en los parametros de carga");
                     continue;
                 }
 
                 //ruta del programa a cargar
                 let path = &format!("input/{}.txt", name_arch);
+                let mut table = table_proccess.lock().unwrap();
+                let mut memory_manager = memory_manager.lock().unwrap();
+
+                //Con paginación el programa se reparte en marcos libres, no se da dirección
+                if memory_manager.paging {
+                    if dir != -1 {
+                        println!("-> Con memoria paginada no se indica dirección de carga");
+                        continue;
+                    }
+
+                    match load_program_paged(path, &mut table, Arc::clone(&ram)) {
+                        Ok(()) => println!("-> Programa cargado paginado correctamente"),
+                        Err(e) => {
+                            println!("Error al cargar el programa: {:?}", e);
+                            continue;
+                        }
+                    }
+                    print_table_process(&table);
+                    Memory_Manager::print_fragmentation(&table);
+                    continue;
+                }
+
+                //Sin dirección se busca un hueco con la politica de asignación
+                if dir == -1 {
+                    let size = match size_program_file(path) {
+                        Ok(size) => size,
+                        Err(e) => {
+                            println!("Error al cargar el programa: {:?}", e);
+                            continue;
+                        }
+                    };
+
+                    //Si no hay hueco se compacta y si no alcanza se sacan procesos a swap
+                    let devices = Swap_Devices {
+                        sender: &tx_dma_kernel,
+                        external_int: &external_interrupts,
+                        disk: &disk,
+                    };
+                    let position = swap_manager.lock().unwrap().make_room(
+                        &mut memory_manager,
+                        &mut table,
+                        &ram,
+                        &devices,
+                        size,
+                        -1,
+                    );
+
+                    dir = match position {
+                        Ok(Some(dir)) => dir,
+                        Err(e) => {
+                            println!("Error al hacer lugar en memoria: {:?}", e);
+                            continue;
+                        }
+                        Ok(None) => {
+                            println!("-> Error no hay un hueco de {} palabras libre", size);
+                            Memory_Manager::print_fragmentation(&table);
+                            continue;
+                        }
+                    };
+                    println!("-> Se asigno la dirección {}", dir);
+                }
+
                 //Funcion para cargar archivo
-                let res = load_program_in_ram(path, &mut table_proccess, Arc::clone(&ram), dir);
+                let res = load_program_in_ram(path, &mut table, Arc::clone(&ram), dir);
                 //Respuesta de la función
                 match res {
                     Ok(()) => println!("-> Programa cargado correctamente"),
@@ -244,7 +529,490 @@ fn main() {
                     }
                 }
 
-                println!("-> Tabla de procesos: {:?}", table_proccess);
+                print_table_process(&table);
+                Memory_Manager::print_fragmentation(&table);
+            }
+            "fit" => match Memory_Manager::policy_by_name(&params_inst) {
+                Some(policy) => {
+                    memory_manager.lock().unwrap().policy = policy;
+                    println!("-> Politica de asignación cambiada a {:?}", policy);
+                }
+                None => {
+                    println!(
+                        "-> Politica actual {:?}, las politicas son: first, best, worst, next",
+                        memory_manager.lock().unwrap().policy
+                    )
+                }
+            },
+            "memmode" => match params_inst.as_str() {
+                "contigua" => {
+                    memory_manager.lock().unwrap().paging = false;
+                    println!("-> Los programas se cargan en memoria contigua");
+                }
+                "paginada" => {
+                    memory_manager.lock().unwrap().paging = true;
+                    println!("-> Los programas se cargan paginados");
+                }
+                _ => println!(
+                    "-> Modo actual: {}, los modos son: contigua, paginada",
+                    if memory_manager.lock().unwrap().paging {
+                        "paginada"
+                    } else {
+                        "contigua"
+                    }
+                ),
+            },
+            "swap" => {
+                let table = table_proccess.lock().unwrap();
+                let mut swap_manager = swap_manager.lock().unwrap();
+                let cils: Vec<i8> = params_inst
+                    .split_whitespace()
+                    .filter_map(|c| c.parse::<i8>().ok())
+                    .collect();
+
+                match cils.as_slice() {
+                    [] if params_inst == "" => (),
+                    [cil_start, cil_end] => {
+                        match swap_manager.set_region(&table, *cil_start, *cil_end) {
+                            Ok(()) => println!("-> Zona de intercambio cambiada"),
+                            Err(e) => println!("->Error {}", e.msg),
+                        }
+                    }
+                    _ => {
+                        println!("->Error en los parametros, se usa swap cil_inicio cil_fin");
+                        continue;
+                    }
+                }
+                swap_manager.print_swap(&table);
+            }
+            "disk" => {
+                let table = table_proccess.lock().unwrap();
+                let mut disk = disk.lock().unwrap();
+                let mut params = params_inst.split_whitespace();
+
+                match (params.next(), params.next()) {
+                    (Some("mount"), Some(file)) => {
+                        //La zona de intercambio esta en el disco actual
+                        if table.iter().any(|p| p.swap_sector >= 0) {
+                            println!("->Error hay procesos en swap en el disco actual");
+                            continue;
+                        }
+                        //Los archivos abiertos apuntan a bloques del disco actual
+                        if let Some(p) = table
+                            .iter()
+                            .find(|p| p.open_files.iter().any(|f| f.is_some()))
+                        {
+                            println!(
+                                "->Error el proceso {} (pid {}) tiene archivos abiertos en el disco actual",
+                                p.name, p.pid
+                            );
+                            continue;
+                        }
+                        match disk.mount(&format!("input/{}", file)) {
+                            Ok(()) => println!("-> Disco montado desde input/{}", file),
+                            Err(e) => println!("->Error {}", e.msg),
+                        }
+                    }
+                    (Some("save"), None) => match disk.save() {
+                        Ok(()) => println!("-> Disco guardado"),
+                        Err(e) => println!("->Error {}", e.msg),
+                    },
+                    (None, None) => {
+                        match &disk.path {
+                            Some(path) => println!("-> Disco montado desde {}", path),
+                            None => println!("-> El disco esta solo en memoria"),
+                        }
+                        let now = disk.now();
+                        println!(
+                            "-> Cabeza en el cilindro {}, sector {} bajo la cabeza, tick {}",
+                            disk.head_cil,
+                            Disk::rotational_position(now),
+                            now
+                        );
+                    }
+                    _ => {
+                        println!("->Error en los parametros, se usa disk mount archivo o disk save")
+                    }
+                }
+            }
+            "vector" => {
+                let params: Vec<i32> = params_inst
+                    .split_whitespace()
+                    .filter_map(|sp| sp.parse::<i32>().ok())
+                    .collect();
+                let mut men = ram.lock().unwrap();
+
+                match params.as_slice() {
+                    [] => {
+                        for vector in 0..VECTOR_SIZE {
+                            match men.readMemory(vector) {
+                                Ok(dir) => println!(
+                                    "-> Entrada {:>2}: rutina en {}",
+                                    vector,
+                                    dir.convert()
+                                ),
+                                Err(e) => println!("->Error {}", e.msg),
+                            }
+                        }
+                    }
+                    //La rutina tiene que estar en el area del sistema, se escribe antes con el kernel
+                    [vector, dir]
+                        if (0..VECTOR_SIZE).contains(vector)
+                            && (VECTOR_SIZE..USER_MEM_START).contains(dir) =>
+                    {
+                        men.writeMemory(
+                            *vector,
+                            Palabra::new(&convert_to_string_format_pal(*dir)).unwrap(),
+                        );
+                        println!("-> Entrada {} del vector apunta a {}", vector, dir);
+                    }
+                    _ => println!(
+                        "-> Error en los parametros, es vector [entrada 0..{} dirección {}..{}]",
+                        VECTOR_SIZE - 1,
+                        VECTOR_SIZE,
+                        USER_MEM_START - 1
+                    ),
+                }
+            }
+            //Lineas del controlador en orden de prioridad
+            "ints" => {
+                let ext = external_interrupts.lock().unwrap();
+                for line in PRIORITY {
+                    println!(
+                        "-> {:>2} {:<14} pendiente: {:<5} {}",
+                        line as i32,
+                        format!("{:?}", line),
+                        ext.is_raised(line),
+                        if !line.maskable() {
+                            "no enmascarable"
+                        } else if ext.is_masked(line) {
+                            "enmascarada"
+                        } else {
+                            "habilitada"
+                        }
+                    );
+                }
+            }
+            "mkboot" => {
+                if params_inst == "" {
+                    println!("-> Error falta el archivo del kernel");
+                    continue;
+                }
+
+                let path = format!("input/{}.txt", params_inst);
+                let result = read_program_file(&path).and_then(|(program, words)| {
+                    Boot::install(&mut disk.lock().unwrap(), &program, &words)
+                });
+                match result {
+                    Ok(()) => println!(
+                        "-> Kernel {} instalado en el cilindro {} pista {}",
+                        params_inst, BOOT_CIL, BOOT_PISTA
+                    ),
+                    Err(e) => println!("->Error {}", e.msg),
+                }
+            }
+            "boot" => {
+                //El kernel toma el procesador desde cero, no puede haber un proceso a medias
+                if table_proccess
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .any(|p| p.state == State_Process::Running)
+                {
+                    println!("->Error hay un proceso en ejecución");
+                    continue;
+                }
+                let registers = Boot::load(&ram, &tx_dma_kernel, &external_interrupts, &disk);
+                let registers = match registers {
+                    Ok(registers) => registers,
+                    Err(e) => {
+                        println!("->Error al arrancar: {}", e.msg);
+                        continue;
+                    }
+                };
+                println!(
+                    "-> Kernel cargado en {}, entrada en {}",
+                    KERNEL_START, registers.psw.pc
+                );
+
+                tx_cpu.send(Registers_Cpu_Config {
+                    mode: Mode_Execute::boot(registers),
+                });
+                match rx_terminal.recv() {
+                    Ok(re) => match re.result_program {
+                        Result_Execute_program::Succes => {
+                            println!("-> El kernel termino el arranque");
+                        }
+                        Result_Execute_program::Error => {
+                            println!("-> Result instrucción : {:?}", re.result_instruction);
+                            println!("-> El kernel termino incorrectamente");
+                        }
+                    },
+                    Err(e) => println!("Error al esperar al cpu: {e}"),
+                }
+            }
+            "ls" => {
+                let mut disk = disk.lock().unwrap();
+                match File_System::list(&mut disk) {
+                    Ok(entries) => {
+                        println!("{:<10} {:>8}", "NOMBRE", "PALABRAS");
+                        for (_, entry) in entries {
+                            println!("{:<10} {:>8}", entry.name, entry.size);
+                        }
+                    }
+                    Err(e) => println!("->Error {}", e.msg),
+                }
+            }
+            "cat" => {
+                let mut disk = disk.lock().unwrap();
+                match File_System::read_file(&mut disk, &params_inst) {
+                    Ok(words) => {
+                        for pal in words {
+                            println!("{}", pal.convert());
+                        }
+                    }
+                    Err(e) => println!("->Error {}", e.msg),
+                }
+            }
+            "rm" => {
+                let table = table_proccess.lock().unwrap();
+                let mut disk = disk.lock().unwrap();
+                let entry = File_System::find(&mut disk, &params_inst).unwrap_or(None);
+                let is_open = table.iter().any(|p| {
+                    p.open_files
+                        .iter()
+                        .any(|f| f.is_some_and(|f| Some(f.entry) == entry))
+                });
+                if is_open {
+                    println!("->Error el archivo {} esta abierto", params_inst);
+                    continue;
+                }
+
+                match File_System::delete(&mut disk, &params_inst) {
+                    Ok(()) => println!("-> Archivo {} borrado", params_inst),
+                    Err(e) => println!("->Error {}", e.msg),
+                }
+            }
+            "import" | "export" => {
+                let mut params = params_inst.split_whitespace();
+                let (host_file, name) = match (params.next(), params.next()) {
+                    (Some(first), second) if current_inst == "import" => {
+                        let name = second.unwrap_or(first.split('.').next().unwrap_or(first));
+                        (first.to_string(), name.to_string())
+                    }
+                    (Some(first), second) => (
+                        second.unwrap_or(&format!("{}.txt", first)).to_string(),
+                        first.to_string(),
+                    ),
+                    _ => {
+                        println!(
+                            "->Error en los parametros, se usa import archivo [nombre] o export nombre [archivo]"
+                        );
+                        continue;
+                    }
+                };
+                let path = format!("input/{}", host_file);
+                let mut disk = disk.lock().unwrap();
+
+                if current_inst == "import" {
+                    let words = match read_words_file(&path) {
+                        Ok(words) => words,
+                        Err(e) => {
+                            println!("->Error {}", e.msg);
+                            continue;
+                        }
+                    };
+                    match File_System::write_file(&mut disk, &name, &words) {
+                        Ok(()) => println!(
+                            "-> Se importo {} como {} ({} palabras)",
+                            path,
+                            name,
+                            words.len()
+                        ),
+                        Err(e) => println!("->Error {}", e.msg),
+                    }
+                } else {
+                    let result = File_System::read_file(&mut disk, &name)
+                        .and_then(|words| write_words_file(&path, &words));
+                    match result {
+                        Ok(()) => println!("-> Se exporto {} a {}", name, path),
+                        Err(e) => println!("->Error {}", e.msg),
+                    }
+                }
+            }
+            "compact" => {
+                let mut table = table_proccess.lock().unwrap();
+                match Memory_Manager::compact(&mut table, &ram) {
+                    Ok(moved) => println!("-> Memoria compactada, se movieron {} procesos", moved),
+                    Err(e) => println!("Error al compactar la memoria: {:?}", e),
+                }
+                Memory_Manager::print_fragmentation(&table);
+            }
+            "mem" => {
+                let table = table_proccess.lock().unwrap();
+                Memory_Manager::print_fragmentation(&table);
+            }
+            "sched" => {
+                if params_inst == "" {
+                    println!(
+                        "-> Planificador actual: {}",
+                        scheduler.lock().unwrap().name()
+                    );
+                    continue;
+                }
+
+                match scheduler_by_name(&params_inst) {
+                    Some(new_scheduler) => {
+                        let mut sched = scheduler.lock().unwrap();
+                        *sched = new_scheduler;
+                        println!("-> Planificador cambiado a {}", sched.name());
+                    }
+                    None => {
+                        println!(
+                            "-> Politica invalida, las politicas son: rr, fcfs, sjf, prioridad, mlfq"
+                        )
+                    }
+                }
+            }
+            "dsched" => {
+                if params_inst == "" {
+                    println!(
+                        "-> Planificador de disco actual: {}",
+                        dma_queue.lock().unwrap().policy.name()
+                    );
+                    continue;
+                }
+
+                match disk_scheduler_by_name(&params_inst) {
+                    Some(new_scheduler) => {
+                        let mut queue = dma_queue.lock().unwrap();
+                        queue.policy = new_scheduler;
+                        println!(
+                            "-> Planificador de disco cambiado a {}",
+                            queue.policy.name()
+                        );
+                    }
+                    None => {
+                        println!(
+                            "-> Politica invalida, las politicas son: fcfs, sstf, scan, cscan, look"
+                        )
+                    }
+                }
+            }
+            "dstats" => {
+                let mut queue = dma_queue.lock().unwrap();
+                if params_inst == "reset" {
+                    queue.reset_stats();
+                    println!("-> Estadisticas del disco reiniciadas");
+                    continue;
+                }
+                queue.print_stats(disk.lock().unwrap().head_cil);
+            }
+            "prio" => {
+                let mut name_prog = String::new();
+                let mut priority: Option<i32> = None;
+
+                for (i, sp) in params_inst.split_whitespace().enumerate() {
+                    match i {
+                        0 => name_prog = sp.to_string(),
+                        1 => priority = sp.parse::<i32>().ok(),
+                        _ => (),
+                    }
+                }
+
+                let priority = match priority {
+                    Some(p) => p,
+                    None => {
+                        println!("-> Error en los parametros de prioridad");
+                        continue;
+                    }
+                };
+
+                let mut table = table_proccess.lock().unwrap();
+                match linear_search_program_mut(&mut table, &name_prog) {
+                    Ok(program) => {
+                        program.priority = priority;
+                        println!("-> Prioridad de {} cambiada a {}", program.name, priority);
+                    }
+                    Err(E) => println!("->Error al buscar el programa: {:?}", E),
+                }
+            }
+            "unload" => {
+                let mut table = table_proccess.lock().unwrap();
+                let pos = match table.iter().position(|p| p.name == params_inst) {
+                    Some(pos) => pos,
+                    None => {
+                        println!("->Error no se encontro el programa {}", params_inst);
+                        continue;
+                    }
+                };
+
+                match table[pos].state {
+                    State_Process::Ready | State_Process::Running | State_Process::Blocked => {
+                        println!(
+                            "->Error el programa {} esta activo, para detenerlo use kill {}",
+                            table[pos].name, table[pos].pid
+                        );
+                        continue;
+                    }
+                    _ => (),
+                }
+                if table[pos].pending_io > 0 {
+                    println!(
+                        "->Error el programa {} tiene E/S pendiente, espere a que termine",
+                        table[pos].name
+                    );
+                    continue;
+                }
+
+                match unload_program(&mut table, pos, Arc::clone(&ram)) {
+                    Ok(()) => println!("-> Programa {} descargado", params_inst),
+                    Err(e) => println!("Error al descargar el programa: {:?}", e),
+                }
+                Memory_Manager::print_fragmentation(&table);
+            }
+            "kill" => {
+                let pid = match params_inst.parse::<i32>() {
+                    Ok(pid) => pid,
+                    Err(_) => {
+                        println!("->Error en el pid a terminar");
+                        continue;
+                    }
+                };
+
+                let mut table = table_proccess.lock().unwrap();
+                let pos = match table.iter().position(|p| p.pid == pid) {
+                    Some(pos) => pos,
+                    None => {
+                        println!("->Error no existe el proceso {}", pid);
+                        continue;
+                    }
+                };
+
+                //Si esta en el procesador, el cpu lo deja de ejecutar al ver que ya no esta en Running
+                let pending_io = table[pos].pending_io > 0;
+                table[pos].state = State_Process::Terminated;
+                table[pos].exit_status = -9;
+                table[pos].end_reason = End_Reason::Killed;
+
+                //Con una E/S pendiente el dma todavia puede escribir en su memoria, se libera despues con unload
+                if pending_io {
+                    println!(
+                        "-> Proceso {} terminado, tiene E/S pendiente, liberar con unload {}",
+                        pid, table[pos].name
+                    );
+                    continue;
+                }
+
+                match unload_program(&mut table, pos, Arc::clone(&ram)) {
+                    Ok(()) => println!("-> Proceso {} terminado y descargado", pid),
+                    Err(e) => println!("Error al descargar el programa: {:?}", e),
+                }
+                Memory_Manager::print_fragmentation(&table);
+            }
+            "ps" => {
+                let table = table_proccess.lock().unwrap();
+                print_table_process(&table);
             }
             "run" => {
                 //Variable de los parametros que recibe
@@ -264,41 +1032,95 @@ fn main() {
                     }
                 }
 
-                match mode.as_str() {
-                    "normal" => {
-                        let result_search = linear_search_program(&table_proccess, &name_prog);
+                let mode_execute = match mode.as_str() {
+                    "normal" => Mode_Execute::normal,
+                    "debugger" => Mode_Execute::debbuger,
+                    _ => {
+                        println!("Modo de ejecucion invalildo");
+                        continue;
+                    }
+                };
+
+                //Se preparan los procesos que entran al round robin, sin nombre entran todos los cargados
+                let mut started = vec![];
+                {
+                    let mut table = table_proccess.lock().unwrap();
+                    if name_prog == "" {
+                        if table.is_empty() {
+                            println!("->Error no hay programas cargados");
+                            continue;
+                        }
+                        for pos in 0..table.len() {
+                            if table[pos].state == State_Process::Running {
+                                continue;
+                            }
+                            //El fin de una E/S pendiente caeria en el proceso ya reiniciado
+                            if table[pos].state == State_Process::Blocked
+                                || table[pos].pending_io > 0
+                            {
+                                println!(
+                                    "-> {} (pid {}) no se reinicia, esta bloqueado o tiene E/S pendiente",
+                                    table[pos].name, table[pos].pid
+                                );
+                                continue;
+                            }
+                            //Si no se puede armar el contexto el proceso no entra con registros viejos
+                            if let Err(e) = init_context_program(&mut table[pos]) {
+                                println!(
+                                    "->Error al preparar el contexto de {}: {}",
+                                    table[pos].name, e.msg
+                                );
+                                continue;
+                            }
+                            table[pos].output.clear();
+                            table[pos].sleep_until = None;
+                            table[pos].sem_wait = None;
+                            table[pos].end_reason = End_Reason::None;
+                            started.push(table[pos].pid);
+                            set_ready(&mut table, pos);
+                        }
+                    } else {
+                        let result_search = linear_search_program_mut(&mut table, &name_prog);
 
                         match result_search {
                             Ok(program) => {
-                                tx_cpu.send(Registers_Cpu_Config {
-                                    mode: Mode_Execute::normal,
-                                    rb: Palabra::new(&convert_to_string_format_pal(
-                                        program.pos_start_mem,
-                                    ))
-                                    .unwrap(),
-                                    rl: Palabra::new(&convert_to_string_format_pal(
-                                        program.pos_start_mem + program.num_instruccions_with_pila,
-                                    ))
-                                    .unwrap(),
-                                    rx: Palabra::new(&convert_to_string_format_pal(
-                                        program.pos_start_mem
-                                            + program.num_instruccions_with_pila / 2,
-                                    ))
-                                    .unwrap(),
-                                    sp: Palabra::new(&convert_to_string_format_pal(
-                                        program.pos_start_mem + program.num_instruccions_with_pila,
-                                    ))
-                                    .unwrap(),
-                                    pc: program.pos_start_mem + (program.pos_start_program - 1),
-                                });
+                                if program.state == State_Process::Running {
+                                    println!("->Error el programa ya esta en ejecución");
+                                    continue;
+                                }
+                                if program.state == State_Process::Blocked || program.pending_io > 0
+                                {
+                                    println!(
+                                        "->Error el programa esta bloqueado o tiene E/S pendiente"
+                                    );
+                                    continue;
+                                }
+                                if let Err(e) = init_context_program(program) {
+                                    println!("->Error al preparar el contexto: {}", e.msg);
+                                    continue;
+                                }
+                                program.output.clear();
+                                program.sleep_until = None;
+                                program.sem_wait = None;
+                                program.end_reason = End_Reason::None;
+                                started.push(program.pid);
+                                let pid = program.pid;
+                                let pos = table.iter().position(|p| p.pid == pid).unwrap();
+                                set_ready(&mut table, pos);
                             }
                             Err(E) => {
                                 println!("->Error al buscar el programa: {:?}", E);
                                 continue;
                             }
                         }
+                    }
+                }
+
+                tx_cpu.send(Registers_Cpu_Config { mode: mode_execute });
 
-                        let result_execute = rx_terminal.recv();
+                let result_execute = rx_terminal.recv();
+                match mode.as_str() {
+                    "normal" => {
                         match result_execute {
                             Ok(re) => match re.result_program {
                                 Result_Execute_program::Succes => {
@@ -311,69 +1133,49 @@ fn main() {
                             },
                             Err(e) => println!("Error al esperar al cpu: {e}"),
                         }
-                    }
-                    "debugger" => {
-                        let result_search = linear_search_program(&table_proccess, &name_prog);
 
-                        match result_search {
-                            Ok(program) => {
-                                tx_cpu.send(Registers_Cpu_Config {
-                                    mode: Mode_Execute::debbuger,
-                                    rb: Palabra::new(&convert_to_string_format_pal(
-                                        program.pos_start_mem,
-                                    ))
-                                    .unwrap(),
-                                    rl: Palabra::new(&convert_to_string_format_pal(
-                                        program.pos_start_mem + program.num_instruccions_with_pila,
-                                    ))
-                                    .unwrap(),
-                                    rx: Palabra::new(&convert_to_string_format_pal(
-                                        program.pos_start_mem
-                                            + (program.num_instruccions_with_pila / 2),
-                                    ))
-                                    .unwrap(),
-                                    sp: Palabra::new(&convert_to_string_format_pal(
-                                        program.pos_start_mem + program.num_instruccions_with_pila,
-                                    ))
-                                    .unwrap(),
-                                    pc: program.pos_start_mem + (program.pos_start_program - 1),
-                                });
-                            }
-                            Err(E) => {
-                                println!("->Error al buscar el programa: {:?}", E);
-                                continue;
+                        //Como termino cada programa de la corrida y su salida, aparte de los mensajes del simulador
+                        let table = table_proccess.lock().unwrap();
+                        let programs = table.iter().filter(|p| started.contains(&p.pid));
+                        for program in programs.clone() {
+                            if program.state == State_Process::Terminated {
+                                println!(
+                                    "-> {} (pid {}) termino con estado {}: {}",
+                                    program.name,
+                                    program.pid,
+                                    program.exit_status,
+                                    program.end_reason.describe()
+                                );
+                            } else {
+                                println!(
+                                    "-> {} (pid {}) quedo en {:?}",
+                                    program.name, program.pid, program.state
+                                );
                             }
                         }
-                        let result_execute = rx_terminal.recv();
-                        match result_execute {
-                            Ok(re) => match re.result_program {
-                                Result_Execute_program::Succes => {
-                                    println!("-> El programa ejecuto la instrucción correctamente");
-                                    println!("-> Dir instrucción : {}", re.dir_inst);
-                                    println!("-> Instrucción : {:?}", re.instruction);
-                                    println!("-> Result instrucción : {:?}", re.result_instruction);
-                                }
-                                Result_Execute_program::Error => {
-                                    println!("-> El programa termino incorrectamente");
-                                }
-                            },
-                            Err(e) => println!("Error al esperar al cpu: {e}"),
+                        for program in programs.filter(|p| !p.output.is_empty()) {
+                            Console::show(program);
                         }
                     }
-                    _ => {
-                        println!("Modo de ejecucion invalildo");
-                        continue;
-                    }
+                    _ => match result_execute {
+                        Ok(re) => match re.result_program {
+                            Result_Execute_program::Succes => {
+                                println!("-> El programa ejecuto la instrucción correctamente");
+                                println!("-> Dir instrucción : {}", re.dir_inst);
+                                println!("-> Instrucción : {:?}", re.instruction);
+                                println!("-> Result instrucción : {:?}", re.result_instruction);
+                            }
+                            Result_Execute_program::Error => {
+                                println!("-> El programa termino incorrectamente");
+                            }
+                        },
+                        Err(e) => println!("Error al esperar al cpu: {e}"),
+                    },
                 }
             }
             "next" => {
                 tx_cpu.send(Registers_Cpu_Config {
                     mode: Mode_Execute::debbuger,
-                    rb: Palabra::new("00000000").unwrap(),
-                    rl: Palabra::new("00000000").unwrap(),
-                    rx: Palabra::new("00000000").unwrap(),
-                    sp: Palabra::new("00000000").unwrap(),
-                    pc: -1,
                 });
 
                 let result_execute = rx_terminal.recv();
@@ -394,16 +1196,58 @@ fn main() {
                 }
             }
 
+            //Salida de consola de un programa o de todos los cargados
+            "out" => {
+                let table = table_proccess.lock().unwrap();
+                if params_inst == "" {
+                    if table.is_empty() {
+                        println!("->Error no hay programas cargados");
+                    }
+                    for program in table.iter() {
+                        Console::show(program);
+                    }
+                } else {
+                    match table.iter().find(|p| p.name == params_inst) {
+                        Some(program) => Console::show(program),
+                        None => println!("->Error no existe el programa {}", params_inst),
+                    }
+                }
+            }
+            "syscalls" => {
+                println!("{:<5} {:<8} ARGUMENTOS", "AC", "NOMBRE");
+                for syscall in SYSCALLS.iter() {
+                    println!("{:<5} {:<8} {}", syscall.cod, syscall.name, syscall.args);
+                }
+            }
+            "sems" => match params_inst.as_str() {
+                "" => print_semaphores(&semaphores.lock().unwrap()),
+                //Solo se borran si nadie esta esperando en uno
+                "reset" => {
+                    let table = table_proccess.lock().unwrap();
+                    if table
+                        .iter()
+                        .any(|p| p.state == State_Process::Blocked && p.sem_wait.is_some())
+                    {
+                        println!("->Error hay procesos esperando en semaforos");
+                        continue;
+                    }
+                    semaphores.lock().unwrap().clear();
+                    println!("-> Semaforos borrados");
+                }
+                _ => println!("->Error en los parametros, se usa sems o sems reset"),
+            },
+            "timer" => {
+                let timer = timer.lock().unwrap();
+                println!(
+                    "-> Tick {}, interrupción de reloj cada {} ticks",
+                    timer.time, timer.period
+                );
+            }
             "exit" => {
                 println!("--- APAGANDO SISTEMA ---");
                 tx_cpu
                     .send(Registers_Cpu_Config {
                         mode: Mode_Execute::off,
-                        rb: Palabra::new(&"00000000").unwrap(),
-                        rl: Palabra::new(&"00000000").unwrap(),
-                        rx: Palabra::new(&"00000000").unwrap(),
-                        sp: Palabra::new(&"00000000").unwrap(),
-                        pc: 0,
                     })
                     .unwrap();
                 break;
@@ -1,14 +1,23 @@
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{BufRead, BufReader, Error},
     os::unix::process,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI32, Ordering},
+    },
 };
 
 use crate::{
-    Programs,
-    hardware::{architecture::Palabra, ram::Ram},
+    Programs, State_Process,
+    hardware::{
+        allocator::Memory_Manager,
+        architecture::Palabra,
+        mmu::{PAGE_SIZE, PAGE_VALID, frame_address, pages_for},
+        ram::Ram,
+        registers::Registros,
+    },
 };
 
 use super::Interrups;
@@ -23,6 +32,11 @@ pub type Result_op = Result<(), Errors>;
 pub enum ContinueOrBreak {
     Continue,
     Break,
+    //Sigue, pero el proceso deja el procesador al volver con iret: listo o bloqueado
+    Yield,
+    Block,
+    //El proceso termino por su cuenta con la llamada exit
+    Exit,
 }
 //Transforma el result de una función en otro, por otro que devuelva mi error definido manualmente
 pub fn convert_result<T, E>(
@@ -57,12 +71,58 @@ pub fn convert_to_string_format_pal(pal: i32) -> String {
     }
 }
 
-pub fn load_program_in_ram(
-    path: &str,
-    table_procces: &mut Vec<Programs>,
-    ram: Arc<Mutex<Ram>>,
-    position_to_load: i32,
-) -> Result<(), Errors> {
+//Lee un archivo del host con un numero por linea, para importarlo al sistema de archivos
+pub fn read_words_file(path: &str) -> Result<Vec<Palabra>, Errors> {
+    let file = convert_result(
+        File::open(path),
+        format!("Error al leer el archivo {}", path),
+        Interrups::EndIO,
+    )?;
+
+    let mut words = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = convert_result(
+            line,
+            "Error al leer el archivo".to_string(),
+            Interrups::EndIO,
+        )?;
+        let number = match line.split_whitespace().next() {
+            Some(number) => number,
+            None => continue,
+        };
+
+        let palabra = number
+            .parse::<i32>()
+            .ok()
+            .and_then(|n| Palabra::new(&convert_to_string_format_pal(n)));
+        words.push(convert_option_result(
+            palabra,
+            format!("Error numero invalido en la linea {}", i + 1),
+            Interrups::EndIO,
+        )?);
+    }
+    Ok(words)
+}
+
+pub fn write_words_file(path: &str, words: &[Palabra]) -> Result_op {
+    let lines: Vec<String> = words.iter().map(|pal| pal.convert().to_string()).collect();
+    convert_result(
+        fs::write(path, lines.join("\n") + "\n"),
+        format!("Error al escribir el archivo {}", path),
+        Interrups::EndIO,
+    )
+}
+
+//Lee el encabezado del programa y devuelve cuantas palabras ocupa en memoria
+pub fn size_program_file(path: &str) -> Result<i32, Errors> {
+    let (process, _) = read_program_file(path)?;
+
+    //Código, datos, pila y la posición de rl
+    Ok(Memory_Manager::size_program(&process))
+}
+
+//Lee el archivo del programa, devuelve el proceso con los datos del encabezado y sus palabras
+pub fn read_program_file(path: &str) -> Result<(Programs, Vec<Palabra>), Errors> {
     let file = File::open(path);
 
     let file = match file {
@@ -76,6 +136,7 @@ pub fn load_program_in_ram(
     let reader = BufReader::new(file);
 
     let mut process: Programs = Programs::new();
+    let mut words: Vec<Palabra> = vec![];
 
     for (i, line) in reader.lines().enumerate() {
         let l = line;
@@ -100,26 +161,14 @@ pub fn load_program_in_ram(
                 for (j, sp) in l.split_whitespace().enumerate() {
                     match j {
                         1 => {
-                            process.num_instruccions_with_pila =
-                                (sp.parse::<i32>().unwrap() * 2) + 1
+                            process.num_instruccions = sp.parse::<i32>().unwrap();
+                            //Sin segmentos declarados todo es código y la pila tiene el mismo tamaño mas uno
+                            process.code_size = process.num_instruccions;
+                            process.stack_size = process.num_instruccions + 1;
                         }
                         _ => (),
                     }
                 }
-
-                {
-                    let ram_s = ram.lock().unwrap();
-                    let position_end = position_to_load + process.num_instruccions_with_pila;
-                    let condition = ram_s.is_empty(position_to_load, position_end)?;
-
-                    if !condition {
-                        return Err(Errors {
-                            msg: format!("Error al Cargar el archivo, posicion ocupada")
-                                .to_string(),
-                            cod: Interrups::EndIO,
-                        });
-                    };
-                }
             }
             2 => {
                 for (j, sp) in l.split_whitespace().enumerate() {
@@ -129,14 +178,41 @@ pub fn load_program_in_ram(
                     }
                 }
             }
+            //Segmentos opcionales antes de las palabras: .Codigo, .Datos y .Pila
+            _ if l.trim_start().starts_with('.') => {
+                let mut parts = l.split_whitespace();
+                let directive = parts.next().unwrap_or("");
+                let size = convert_option_result(
+                    parts.next().and_then(|sp| sp.parse::<i32>().ok()),
+                    format!("Error tamaño de segmento invalido en la linea {}", i + 1),
+                    Interrups::EndIO,
+                )?;
+
+                match directive {
+                    ".Codigo" => {
+                        process.code_size = size;
+                        process.code_protected = true;
+                    }
+                    ".Datos" => process.data_size = size,
+                    ".Pila" => process.stack_size = size,
+                    _ => {
+                        return Err(Errors {
+                            msg: format!("Error directiva desconocida en la linea {}", i + 1),
+                            cod: Interrups::EndIO,
+                        });
+                    }
+                }
+            }
             _ => {
                 for (j, sp) in l.split_whitespace().enumerate() {
                     match j {
                         0 => {
-                            process.pos_start_mem = position_to_load;
-                            let mut ram = ram.lock().unwrap();
-                            let palabra = Palabra::new(sp).unwrap();
-                            ram.writeMemory(position_to_load + (i as i32 - 3), palabra)?;
+                            let palabra = convert_option_result(
+                                Palabra::new(sp),
+                                format!("Error palabra invalida en la linea {}", i + 1),
+                                Interrups::EndIO,
+                            )?;
+                            words.push(palabra);
                         }
                         _ => {}
                     }
@@ -144,10 +220,152 @@ pub fn load_program_in_ram(
             }
         }
     }
+
+    if process.code_size < 0 || process.data_size < 0 || process.stack_size < 1 {
+        return Err(Errors {
+            msg: "Error tamaño de segmento invalido".to_string(),
+            cod: Interrups::EndIO,
+        });
+    }
+    if words.len() as i32 > process.stack_base() {
+        return Err(Errors {
+            msg: "Error el programa tiene mas palabras que el código y los datos".to_string(),
+            cod: Interrups::EndIO,
+        });
+    }
+
+    //rl queda despues de la pila
+    process.num_instruccions_with_pila = process.stack_base() + process.stack_size;
+    Ok((process, words))
+}
+
+pub fn load_program_in_ram(
+    path: &str,
+    table_procces: &mut Vec<Programs>,
+    ram: Arc<Mutex<Ram>>,
+    position_to_load: i32,
+) -> Result<(), Errors> {
+    let (mut process, words) = read_program_file(path)?;
+
+    {
+        let mut ram = ram.lock().unwrap();
+        let position_end = position_to_load + process.num_instruccions_with_pila;
+        let condition = ram.is_empty(position_to_load, position_end)?;
+
+        if !condition {
+            return Err(Errors {
+                msg: format!("Error al Cargar el archivo, posicion ocupada").to_string(),
+                cod: Interrups::EndIO,
+            });
+        };
+
+        for (i, palabra) in words.iter().enumerate() {
+            ram.writeMemory(position_to_load + i as i32, *palabra)?;
+        }
+    }
+
+    process.pos_start_mem = position_to_load;
+    process.pid = next_pid();
     table_procces.push(process);
     Ok(())
 }
 
+//Siguiente pid a asignar, solo avanza para que un pid no se repita aunque se descargue el proceso que lo tenia
+static NEXT_PID: AtomicI32 = AtomicI32::new(1);
+
+pub fn next_pid() -> i32 {
+    NEXT_PID.fetch_add(1, Ordering::Relaxed)
+}
+
+//Carga el programa con paginación por demanda: solo las paginas de la pila quedan en memoria,
+//el resto se trae desde la imagen del programa cuando hay fallo de pagina
+pub fn load_program_paged(
+    path: &str,
+    table_procces: &mut Vec<Programs>,
+    ram: Arc<Mutex<Ram>>,
+) -> Result<(), Errors> {
+    let (mut process, words) = read_program_file(path)?;
+
+    let size = Memory_Manager::size_program(&process);
+    let pages = pages_for(size);
+    let ptbr = convert_option_result(
+        Memory_Manager::allocate_page_table(table_procces, pages),
+        "Error no hay lugar para la tabla de paginas".to_string(),
+        Interrups::DirInv,
+    )?;
+
+    let mut image = words;
+    image.resize(
+        (pages * PAGE_SIZE) as usize,
+        Palabra::new("00000000").unwrap(),
+    );
+
+    process.ptbr = ptbr;
+    process.pages = pages;
+    process.image = image;
+
+    //Las paginas de la pila se cargan al inicio para poder salvar el contexto en las interrupciones
+    let first_stack_page = process.stack_base() / PAGE_SIZE;
+    let mut free_frames = Memory_Manager::free_frames(table_procces);
+    if free_frames.len() < (pages - first_stack_page) as usize {
+        return Err(Errors {
+            msg: "Error no hay marcos libres para la pila del programa".to_string(),
+            cod: Interrups::DirInv,
+        });
+    }
+
+    {
+        let mut ram = ram.lock().unwrap();
+        for page in 0..pages {
+            ram.writeMemory(ptbr + page, Palabra::new("00000000").unwrap())?;
+        }
+        for page in first_stack_page..pages {
+            let frame = free_frames.remove(0);
+            load_page(&mut process, page, frame, &mut ram)?;
+        }
+    }
+
+    process.pid = next_pid();
+    table_procces.push(process);
+    Ok(())
+}
+
+//Copia la pagina desde la imagen del proceso al marco y la marca valida en la tabla de paginas
+pub fn load_page(program: &mut Programs, page: i32, frame: i32, ram: &mut Ram) -> Result_op {
+    let base = frame_address(frame);
+    for offset in 0..PAGE_SIZE {
+        let palabra = program.image[(page * PAGE_SIZE + offset) as usize];
+        ram.writeMemory(base + offset, palabra)?;
+    }
+
+    let entry = convert_option_result(
+        Palabra::new(&convert_to_string_format_pal(PAGE_VALID + frame)),
+        "Error en la entrada de la tabla de paginas".to_string(),
+        Interrups::DirInv,
+    )?;
+    ram.writeMemory(program.ptbr + page, entry)?;
+    program.page_frames.push((page, frame));
+    Ok(())
+}
+
+//Saca la pagina del marco guardando su contenido en la imagen del proceso
+pub fn evict_page(program: &mut Programs, page: i32, ram: &mut Ram) -> Result<i32, Errors> {
+    let pos = convert_option_result(
+        program.page_frames.iter().position(|&(p, _)| p == page),
+        "Error la pagina no esta en memoria".to_string(),
+        Interrups::DirInv,
+    )?;
+    let (_, frame) = program.page_frames.remove(pos);
+
+    let base = frame_address(frame);
+    for offset in 0..PAGE_SIZE {
+        program.image[(page * PAGE_SIZE + offset) as usize] = ram.readMemory(base + offset)?;
+        ram.writeMemory(base + offset, Palabra::new("00000000").unwrap())?;
+    }
+    ram.writeMemory(program.ptbr + page, Palabra::new("00000000").unwrap())?;
+    Ok(frame)
+}
+
 pub fn linear_search_program<'a>(
     table_process: &'a Vec<Programs>,
     name_program: &String,
@@ -166,3 +384,160 @@ pub fn linear_search_program<'a>(
         cod: Interrups::InstInv,
     })
 }
+
+pub fn linear_search_program_mut<'a>(
+    table_process: &'a mut Vec<Programs>,
+    name_program: &String,
+) -> Result<&'a mut Programs, Errors> {
+    for program in table_process {
+        if program.name == *name_program {
+            return Ok(program);
+        }
+    }
+    Err(Errors {
+        msg: format!(
+            "No se encontro programa el programa {}, en la tabla de procesos",
+            name_program
+        )
+        .to_string(),
+        cod: Interrups::InstInv,
+    })
+}
+
+//Arma el contexto inicial del proceso (rb, rd, rx, rl, sp y pc) a partir de donde se cargo en memoria
+pub fn init_context_program(program: &mut Programs) -> Result_op {
+    let mut context = Registros::new();
+
+    //Con paginación el espacio del proceso es logico y empieza en 0
+    let pos_start_mem = if program.ptbr >= 0 {
+        0
+    } else {
+        program.pos_start_mem
+    };
+
+    context.set_rb(convert_option_result(
+        Palabra::new(&convert_to_string_format_pal(pos_start_mem)),
+        "Error al calcular rb del programa".to_string(),
+        Interrups::DirInv,
+    )?)?;
+    context.set_rl(convert_option_result(
+        Palabra::new(&convert_to_string_format_pal(
+            pos_start_mem + program.num_instruccions_with_pila,
+        )),
+        "Error al calcular rl del programa".to_string(),
+        Interrups::DirInv,
+    )?)?;
+    context.set_rx(convert_option_result(
+        Palabra::new(&convert_to_string_format_pal(
+            pos_start_mem + program.stack_base(),
+        )),
+        "Error al calcular rx del programa".to_string(),
+        Interrups::DirInv,
+    )?)?;
+    context.set_rd(convert_option_result(
+        Palabra::new(&convert_to_string_format_pal(
+            pos_start_mem + program.code_size,
+        )),
+        "Error al calcular rd del programa".to_string(),
+        Interrups::DirInv,
+    )?)?;
+    context.set_sp(convert_option_result(
+        Palabra::new(&convert_to_string_format_pal(
+            pos_start_mem + program.num_instruccions_with_pila,
+        )),
+        "Error al calcular sp del programa".to_string(),
+        Interrups::DirInv,
+    )?)?;
+    context
+        .psw
+        .set_pc(pos_start_mem + (program.pos_start_program - 1))?;
+
+    program.context = context;
+    //El proceso arranca sin archivos abiertos
+    program.open_files.clear();
+    Ok(())
+}
+
+//Libera la memoria del proceso desde rb hasta rl y lo saca de la tabla de procesos
+pub fn unload_program(
+    table_process: &mut Vec<Programs>,
+    pos: usize,
+    ram: Arc<Mutex<Ram>>,
+) -> Result_op {
+    let program = &mut table_process[pos];
+    {
+        let mut ram = ram.lock().unwrap();
+        if program.ptbr >= 0 {
+            let pages: Vec<i32> = program.page_frames.iter().map(|&(page, _)| page).collect();
+            for page in pages {
+                evict_page(program, page, &mut ram)?;
+            }
+            for page in 0..program.pages {
+                ram.writeMemory(program.ptbr + page, Palabra::new("00000000").unwrap())?;
+            }
+        } else if program.swap_sector < 0 {
+            ram.clear(
+                program.pos_start_mem,
+                program.pos_start_mem + program.num_instruccions_with_pila,
+            )?;
+        }
+    }
+
+    table_process.remove(pos);
+    Ok(())
+}
+
+//Pasa el proceso a listo, marcando su orden de llegada para los planificadores
+pub fn set_ready(table_process: &mut Vec<Programs>, pos: usize) {
+    let last = table_process
+        .iter()
+        .map(|p| p.ready_since)
+        .max()
+        .unwrap_or(0);
+
+    table_process[pos].state = State_Process::Ready;
+    table_process[pos].ready_since = last + 1;
+}
+
+pub fn print_table_process(table_process: &Vec<Programs>) {
+    println!(
+        "{:<5} {:<12} {:<17} {:>6} {:>6} {:>6} {:>6} {:>5} {:>5} {:>6} {:>8} {:>6} {:>6}  {}",
+        "PID",
+        "NOMBRE",
+        "ESTADO",
+        "RB",
+        "RL",
+        "PC",
+        "SP",
+        "PRIO",
+        "NIVEL",
+        "SALIDA",
+        "INSTRUC",
+        "CPU",
+        "ESPERA",
+        "MOTIVO"
+    );
+    for program in table_process {
+        println!(
+            "{:<5} {:<12} {:<17} {:>6} {:>6} {:>6} {:>6} {:>5} {:>5} {:>6} {:>8} {:>6} {:>6}  {}",
+            program.pid,
+            program.name,
+            if program.swap_sector >= 0 {
+                format!("{:?} (swap)", program.state)
+            } else {
+                format!("{:?}", program.state)
+            },
+            program.context.rb.convert(),
+            program.context.rl.convert(),
+            program.context.psw.pc,
+            program.context.sp.convert(),
+            program.priority,
+            program.queue_level,
+            program.exit_status,
+            program.instructions_executed,
+            program.dispatches,
+            program.wait_time,
+            program.end_reason.describe()
+        );
+    }
+}